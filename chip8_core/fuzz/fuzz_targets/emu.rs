@@ -0,0 +1,65 @@
+// Arbitrary ROMs, keys and machine setups, with the checks from tests/properties.rs.
+//
+//     cargo +nightly fuzz run emu
+//
+// Without cargo-fuzz, `cargo build` here gives a plain libFuzzer binary that still runs
+// inputs and reproduces crashes, just without coverage guidance:
+//
+//     target/debug/emu artifacts/emu/crash-...
+#![no_main]
+
+use arbitrary::Arbitrary;
+use chip8_core::*;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_TICKS: usize = 10_000;
+const TICKS_PER_FRAME: usize = 10;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    mode: u8,
+    quirks: [bool; 5],
+    seed: u64,
+    keys: Vec<(u16, u8, bool)>,     // Tick, key, pressed
+    rom: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let [vf_reset, memory, shift, jump, clip] = input.quirks;
+    let mut emu = Emu::with_quirks(Quirks { vf_reset, memory, shift, jump, clip });
+    emu.set_mode(match input.mode % 3 {
+        0 => Mode::Chip8,
+        1 => Mode::SuperChip,
+        _ => Mode::XoChip,
+    });
+    emu.set_seed(input.seed);
+    let font = emu.ram()[..FONT_END as usize].to_vec();
+    if emu.load(&input.rom).is_err() {
+        assert!(START_ADDR as usize + input.rom.len() > emu.ram().len());
+        return;
+    }
+
+    // Stores to the font are allowed, anything else changing it is a bug
+    emu.add_watchpoint(0..=FONT_END - 1, Access::Write);
+    let mut font_written = false;
+
+    for tick in 0..MAX_TICKS {
+        for &(_, key, pressed) in input.keys.iter().filter(|(t, ..)| *t as usize == tick) {
+            emu.keypress(key as usize % 16, pressed);
+        }
+        let pc = emu.pc() as usize;
+        match emu.run(1) {
+            Ok(Stop::Watchpoint { .. }) => font_written = true,
+            Ok(Stop::Exited) | Err(_) => break,
+            Ok(_) => (),
+        }
+        assert!(pc + 2 <= emu.ram().len(), "ran an instruction from outside RAM at {:#X}", pc);
+        assert!(emu.stack().len() <= STACK_SIZE);
+        if !font_written {
+            assert_eq!(emu.ram()[..FONT_END as usize], font[..], "font changed without a store");
+        }
+        if (tick + 1) % TICKS_PER_FRAME == 0 {
+            emu.tick_timers();
+        }
+    }
+});
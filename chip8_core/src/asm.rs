@@ -0,0 +1,505 @@
+/*
+    Assembler : source -> ROM bytes
+
+    Cowgod mnemonics, the same ones the disassembler prints, so a Cowgod listing assembles back
+    into the bytes it came from. Two passes: the first splits lines into statements, reads
+    includes and gives every label its address, the second evaluates operands and encodes.
+
+        SPEED   EQU 2                   ; constant
+        start:  LD V0, SPEED            ; label, then an instruction
+                LD I, sprite
+                DRW V0, V1, 5
+        loop:   JP loop
+        sprite: DB 0x20, 0x60, 0x20, 0x20, 0x70
+                DW start + 2
+                INCLUDE "more.asm"      ; relative to the including file
+
+    Numbers are decimal, 0x / $ / # hex or 0b binary, and operands can add or subtract symbols.
+    `LD I, LONG addr` is XO-CHIP's F000 NNNN.
+ */
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{encode, AsmError, Instruction, START_ADDR};
+
+// Everything the second pass accepts, anything else is reported as unknown
+const MNEMONICS: [&str; 32] = [
+    "CLS", "RET", "SYS", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE",
+    "SNE", "SAVE", "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND",
+    "DRW", "SKP", "SKNP", "PLANE", "AUDIO", "PITCH",
+];
+
+// Operand names that can't be used as symbols
+const RESERVED: [&str; 10] = ["I", "DT", "ST", "K", "F", "HF", "B", "R", "LONG", "EQU"];
+
+// Highest address a program can reach, the end of XO-CHIP's 64K
+const MAX_ADDR: u32 = 0x10000;
+
+// Assemble source text, includes are looked up from the working directory
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut asm = Assembler::new();
+    asm.read(source, None)?;
+    asm.finish()
+}
+
+// Assemble a file, includes are looked up next to the file that includes them
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, AsmError> {
+    let mut asm = Assembler::new();
+    asm.include(path, None)?;
+    asm.finish()
+}
+
+// Where a statement came from
+#[derive(Debug, Clone)]
+struct Loc {
+    file: Option<PathBuf>,
+    line: usize,
+}
+
+impl Loc {
+    fn error(&self, column: usize, message: impl Into<String>) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            line: self.line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+// One comma separated operand, column is where its text starts
+#[derive(Debug, Clone)]
+struct Operand {
+    text: String,
+    column: usize,
+}
+
+enum Kind {
+    Op { mnemonic: String, column: usize, operands: Vec<Operand> },
+    Bytes(Vec<Operand>),
+    Words(Vec<Operand>),
+}
+
+struct Statement {
+    loc: Loc,
+    kind: Kind,
+}
+
+// Operand shapes, used to pick the encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arg {
+    Reg(u8),    // V0 - VF
+    I,
+    IndirectI,  // [I]
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Long,       // LONG expr
+    Value,      // Any other expression
+}
+
+struct Assembler {
+    statements: Vec<Statement>,
+    symbols: HashMap<String, i64>,      // Labels and constants
+    addr: u32,                          // Address of the next statement during the first pass
+    reading: Vec<PathBuf>,              // Files being read, to catch include cycles
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Self {
+            statements: Vec::new(),
+            symbols: HashMap::new(),
+            addr: START_ADDR as u32,
+            reading: Vec::new(),
+        }
+    }
+
+    // Read a file, from loc when it's an INCLUDE
+    fn include(&mut self, path: &Path, from: Option<(&Loc, usize)>) -> Result<(), AsmError> {
+        let fail = |message: String| match from {
+            Some((loc, column)) => loc.error(column, message),
+            None => AsmError { file: Some(path.to_path_buf()), line: 0, column: 0, message },
+        };
+
+        let canonical = fs::canonicalize(path).map_err(|e| fail(format!("can't read {}: {}", path.display(), e)))?;
+        if self.reading.contains(&canonical) {
+            return Err(fail(format!("{} includes itself", path.display())));
+        }
+        let source = fs::read_to_string(path).map_err(|e| fail(format!("can't read {}: {}", path.display(), e)))?;
+
+        self.reading.push(canonical);
+        self.read(&source, Some(path))?;
+        self.reading.pop();
+        Ok(())
+    }
+
+    // First pass over one source file
+    fn read(&mut self, source: &str, file: Option<&Path>) -> Result<(), AsmError> {
+        for (n, text) in source.lines().enumerate() {
+            let loc = Loc { file: file.map(Path::to_path_buf), line: n + 1 };
+            let code = strip_comment(text);
+            let mut words = Words { text: code, pos: 0 };
+
+            // Optional label
+            let mut word = words.next();
+            if let Some((name, column)) = word {
+                if let Some(label) = name.strip_suffix(':') {
+                    self.define(&loc, column, label, self.addr as i64)?;
+                    word = words.next();
+                }
+            }
+            let Some((first, column)) = word else { continue };
+            let rest = words.rest();
+
+            // NAME EQU value
+            let mut after = Words { text: rest.0, pos: 0 };
+            if let Some((equ, _)) = after.next() {
+                if equ.eq_ignore_ascii_case("EQU") {
+                    let (expr, expr_column) = after.rest();
+                    let trimmed = expr.trim_start();
+                    let operand = Operand {
+                        text: trimmed.trim_end().to_string(),
+                        column: rest.1 + expr_column - 1 + (expr.len() - trimmed.len()),
+                    };
+                    let value = self.eval(&loc, &operand)?;
+                    self.define(&loc, column, first, value)?;
+                    continue;
+                }
+            }
+
+            let operands = split_operands(rest.0, rest.1);
+            let directive = first.to_ascii_uppercase();
+            let (kind, size) = match directive.as_str() {
+                "INCLUDE" => {
+                    let [operand] = operands.as_slice() else {
+                        return Err(loc.error(column, "INCLUDE takes one file name"));
+                    };
+                    let name = operand.text.trim_matches('"');
+                    let path = match file.and_then(Path::parent) {
+                        Some(dir) => dir.join(name),
+                        None => PathBuf::from(name),
+                    };
+                    self.include(&path, Some((&loc, operand.column)))?;
+                    continue;
+                },
+                "DB" | "DW" => {
+                    if operands.is_empty() {
+                        return Err(loc.error(column, format!("{} needs at least one value", directive)));
+                    }
+                    if directive == "DB" {
+                        let size = operands.len() as u32;
+                        (Kind::Bytes(operands), size)
+                    } else {
+                        let size = 2 * operands.len() as u32;
+                        (Kind::Words(operands), size)
+                    }
+                },
+                _ => {
+                    let long = operands.get(1).is_some_and(|op| classify(&op.text) == Arg::Long);
+                    let size = if long { 4 } else { 2 };
+                    (Kind::Op { mnemonic: directive, column, operands }, size)
+                },
+            };
+
+            self.addr += size;
+            if self.addr > MAX_ADDR {
+                return Err(loc.error(column, "program doesn't fit in memory"));
+            }
+            self.statements.push(Statement { loc, kind });
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, loc: &Loc, column: usize, name: &str, value: i64) -> Result<(), AsmError> {
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+        if !valid {
+            return Err(loc.error(column, format!("`{}` isn't a valid symbol name", name)));
+        }
+        if classify(name) != Arg::Value || RESERVED.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+            return Err(loc.error(column, format!("`{}` is reserved", name)));
+        }
+        if self.symbols.insert(name.to_string(), value).is_some() {
+            return Err(loc.error(column, format!("`{}` is already defined", name)));
+        }
+        Ok(())
+    }
+
+    // Second pass
+    fn finish(self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::new();
+        for statement in &self.statements {
+            let loc = &statement.loc;
+            match &statement.kind {
+                Kind::Bytes(operands) => {
+                    for operand in operands {
+                        rom.push(self.value(loc, operand, 8)? as u8);
+                    }
+                },
+                Kind::Words(operands) => {
+                    for operand in operands {
+                        rom.extend_from_slice(&self.value(loc, operand, 16)?.to_be_bytes());
+                    }
+                },
+                Kind::Op { mnemonic, column, operands } => {
+                    let (instr, next) = self.instruction(loc, mnemonic, *column, operands)?;
+                    rom.extend_from_slice(&encode(instr).to_be_bytes());
+                    if let Some(word) = next {
+                        rom.extend_from_slice(&word.to_be_bytes());
+                    }
+                },
+            }
+        }
+        Ok(rom)
+    }
+
+    // Pick the instruction for a mnemonic and its operands, plus the extra word of F000 NNNN
+    fn instruction(&self, loc: &Loc, mnemonic: &str, column: usize, operands: &[Operand]) -> Result<(Instruction, Option<u16>), AsmError> {
+        use Instruction::*;
+        use Arg::{Reg, Value};
+
+        let args: Vec<Arg> = operands.iter().map(|op| classify(&op.text)).collect();
+        let nibble = |i: usize| self.value(loc, &operands[i], 4).map(|v| v as u8);
+        let byte = |i: usize| self.value(loc, &operands[i], 8).map(|v| v as u8);
+        let addr = |i: usize| self.value(loc, &operands[i], 12);
+
+        let instr = match (mnemonic, args.as_slice()) {
+            ("CLS", []) => Cls,
+            ("RET", []) => Ret,
+            ("SYS", [Value]) => Sys { nnn: addr(0)? },
+            ("SCD", [Value]) => ScrollDown { n: nibble(0)? },
+            ("SCU", [Value]) => ScrollUp { n: nibble(0)? },
+            ("SCR", []) => ScrollRight,
+            ("SCL", []) => ScrollLeft,
+            ("EXIT", []) => Exit,
+            ("LOW", []) => Lores,
+            ("HIGH", []) => Hires,
+            ("JP", [Value]) => Jump { nnn: addr(0)? },
+            ("JP", [Reg(0), Value]) => JumpOffset { nnn: addr(1)? },
+            ("CALL", [Value]) => Call { nnn: addr(0)? },
+            ("SE", [Reg(x), Value]) => SkipEqImm { x: *x, nn: byte(1)? },
+            ("SE", [Reg(x), Reg(y)]) => SkipEqReg { x: *x, y: *y },
+            ("SNE", [Reg(x), Value]) => SkipNeImm { x: *x, nn: byte(1)? },
+            ("SNE", [Reg(x), Reg(y)]) => SkipNeReg { x: *x, y: *y },
+            ("SAVE", [Reg(x), Reg(y)]) => SaveRange { x: *x, y: *y },
+            ("LOAD", [Reg(x), Reg(y)]) => LoadRange { x: *x, y: *y },
+            ("LD", [Reg(x), Value]) => LoadImm { x: *x, nn: byte(1)? },
+            ("LD", [Reg(x), Reg(y)]) => Move { x: *x, y: *y },
+            ("LD", [Arg::I, Value]) => LoadI { nnn: addr(1)? },
+            ("LD", [Arg::I, Arg::Long]) => {
+                let op = &operands[1];
+                let text = &op.text["LONG".len()..];
+                let expr = Operand {
+                    text: text.trim_start().to_string(),
+                    column: op.column + "LONG".len() + text.len() - text.trim_start().len(),
+                };
+                return Ok((LoadILong, Some(self.value(loc, &expr, 16)?)));
+            },
+            ("LD", [Reg(x), Arg::Dt]) => GetDelay { x: *x },
+            ("LD", [Reg(x), Arg::K]) => WaitKey { x: *x },
+            ("LD", [Arg::Dt, Reg(x)]) => SetDelay { x: *x },
+            ("LD", [Arg::St, Reg(x)]) => SetSound { x: *x },
+            ("LD", [Arg::F, Reg(x)]) => Font { x: *x },
+            ("LD", [Arg::Hf, Reg(x)]) => BigFont { x: *x },
+            ("LD", [Arg::B, Reg(x)]) => Bcd { x: *x },
+            ("LD", [Arg::IndirectI, Reg(x)]) => Store { x: *x },
+            ("LD", [Reg(x), Arg::IndirectI]) => Load { x: *x },
+            ("LD", [Arg::R, Reg(x)]) => SaveFlags { x: *x },
+            ("LD", [Reg(x), Arg::R]) => LoadFlags { x: *x },
+            ("ADD", [Reg(x), Value]) => AddImm { x: *x, nn: byte(1)? },
+            ("ADD", [Reg(x), Reg(y)]) => Add { x: *x, y: *y },
+            ("ADD", [Arg::I, Reg(x)]) => AddI { x: *x },
+            ("OR", [Reg(x), Reg(y)]) => Or { x: *x, y: *y },
+            ("AND", [Reg(x), Reg(y)]) => And { x: *x, y: *y },
+            ("XOR", [Reg(x), Reg(y)]) => Xor { x: *x, y: *y },
+            ("SUB", [Reg(x), Reg(y)]) => Sub { x: *x, y: *y },
+            ("SUBN", [Reg(x), Reg(y)]) => SubN { x: *x, y: *y },
+            ("SHR", [Reg(x), Reg(y)]) => Shr { x: *x, y: *y },
+            ("SHR", [Reg(x)]) => Shr { x: *x, y: *x },
+            ("SHL", [Reg(x), Reg(y)]) => Shl { x: *x, y: *y },
+            ("SHL", [Reg(x)]) => Shl { x: *x, y: *x },
+            ("RND", [Reg(x), Value]) => Random { x: *x, nn: byte(1)? },
+            ("DRW", [Reg(x), Reg(y), Value]) => Draw { x: *x, y: *y, n: nibble(2)? },
+            ("SKP", [Reg(x)]) => SkipKey { x: *x },
+            ("SKNP", [Reg(x)]) => SkipNotKey { x: *x },
+            ("PLANE", [Value]) => Plane { n: nibble(0)? },
+            ("AUDIO", []) => Audio,
+            ("PITCH", [Reg(x)]) => Pitch { x: *x },
+            (m, _) if MNEMONICS.contains(&m) => {
+                return Err(loc.error(column, format!("invalid operands for {}", m)));
+            },
+            (m, _) => return Err(loc.error(column, format!("unknown instruction `{}`", m))),
+        };
+        Ok((instr, None))
+    }
+
+    // Evaluate an operand that has to fit in bits. Negative values down to -2^(bits-1) wrap
+    fn value(&self, loc: &Loc, operand: &Operand, bits: u32) -> Result<u16, AsmError> {
+        let value = self.eval(loc, operand)?;
+        let max = (1i64 << bits) - 1;
+        let min = -(1i64 << (bits - 1));
+        if value < min || value > max {
+            return Err(loc.error(operand.column, format!("{} doesn't fit in {} bits", value, bits)));
+        }
+        Ok((value & max) as u16)
+    }
+
+    // Sum of numbers and symbols joined by + and -
+    fn eval(&self, loc: &Loc, operand: &Operand) -> Result<i64, AsmError> {
+        let chars: Vec<(usize, char)> = operand.text.char_indices().collect();
+        let column = |i: usize| operand.column + chars.get(i).map_or(operand.text.len(), |&(at, _)| at);
+
+        let mut total: i64 = 0;
+        let mut sign = 1;
+        let mut want_term = true;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i].1;
+            if c.is_whitespace() {
+                i += 1;
+            } else if want_term && (c == '-' || c == '+') {
+                if c == '-' {
+                    sign = -sign;
+                }
+                i += 1;
+            } else if want_term {
+                let start = i;
+                while i < chars.len() && is_term_char(chars[i].1) {
+                    i += 1;
+                }
+                if start == i {
+                    return Err(loc.error(column(start), format!("unexpected `{}`", c)));
+                }
+                let term: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+                let value = match number(&term) {
+                    Some(value) => value,
+                    None if term.starts_with(|c: char| c.is_ascii_digit() || c == '$' || c == '#') => {
+                        return Err(loc.error(column(start), format!("invalid number `{}`", term)));
+                    },
+                    None => match self.symbols.get(&term) {
+                        Some(value) => *value,
+                        None => return Err(loc.error(column(start), format!("undefined symbol `{}`", term))),
+                    },
+                };
+                total = value.checked_mul(sign).and_then(|value| total.checked_add(value))
+                    .ok_or_else(|| loc.error(operand.column, "value out of range"))?;
+                sign = 1;
+                want_term = false;
+            } else if c == '+' || c == '-' {
+                sign = if c == '-' { -1 } else { 1 };
+                want_term = true;
+                i += 1;
+            } else {
+                return Err(loc.error(column(i), format!("unexpected `{}`", c)));
+            }
+        }
+
+        if want_term {
+            return Err(loc.error(column(chars.len()), "expected a value"));
+        }
+        Ok(total)
+    }
+}
+
+// Whitespace separated words of a line, with their 1-based columns
+struct Words<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Words<'a> {
+    fn next(&mut self) -> Option<(&'a str, usize)> {
+        let rest = &self.text[self.pos..];
+        let start = self.pos + (rest.len() - rest.trim_start().len());
+        if start == self.text.len() {
+            return None;
+        }
+        let len = self.text[start..].find(char::is_whitespace).unwrap_or(self.text.len() - start);
+        self.pos = start + len;
+        Some((&self.text[start..start + len], start + 1))
+    }
+
+    // Everything after the last word, and the column it starts at
+    fn rest(&self) -> (&'a str, usize) {
+        (&self.text[self.pos..], self.pos + 1)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+// Split on commas, column is where text starts in the line
+fn split_operands(text: &str, column: usize) -> Vec<Operand> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut operands = Vec::new();
+    let mut start = 0;
+    for part in text.split(',') {
+        let trimmed = part.trim_start();
+        operands.push(Operand {
+            text: trimmed.trim_end().to_string(),
+            column: column + start + (part.len() - trimmed.len()),
+        });
+        start += part.len() + 1;
+    }
+    operands
+}
+
+fn classify(text: &str) -> Arg {
+    let upper = text.to_ascii_uppercase();
+    match upper.as_str() {
+        "I" => return Arg::I,
+        "[I]" => return Arg::IndirectI,
+        "DT" => return Arg::Dt,
+        "ST" => return Arg::St,
+        "K" => return Arg::K,
+        "F" => return Arg::F,
+        "HF" => return Arg::Hf,
+        "B" => return Arg::B,
+        "R" => return Arg::R,
+        _ => (),
+    }
+    if upper.len() == 2 && upper.starts_with('V') {
+        if let Some(x) = upper[1..].chars().next().and_then(|c| c.to_digit(16)) {
+            return Arg::Reg(x as u8);
+        }
+    }
+    if upper.starts_with("LONG") && upper[4..].starts_with(char::is_whitespace) {
+        return Arg::Long;
+    }
+    Arg::Value
+}
+
+fn is_term_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$' || c == '#'
+}
+
+fn number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+    let (digits, radix) = if let Some(hex) = lower.strip_prefix("0x").or(lower.strip_prefix('$')).or(lower.strip_prefix('#')) {
+        (hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        (bin, 2)
+    } else if lower.starts_with(|c: char| c.is_ascii_digit()) {
+        (lower.as_str(), 10)
+    } else {
+        return None;
+    };
+    i64::from_str_radix(digits, radix).ok()
+}
@@ -0,0 +1,90 @@
+/*
+    Audio helpers for frontends : a square wave for the sound timer and a WAV file writer
+ */
+use std::io::{self, Seek, SeekFrom, Write};
+
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+pub const SAMPLE_RATE: u32 = 44100;     // What the frontends play and dump at
+
+// Square wave generator, keeps its phase between calls so buffers join up without clicks
+pub struct SquareWave {
+    pub frequency: f32,     // Hz
+    pub volume: f32,        // 0.0 - 1.0
+    pub muted: bool,
+    phase: f32,             // 0.0 - 1.0 through the current period
+}
+
+impl SquareWave {
+    pub fn new(frequency: f32, volume: f32) -> Self {
+        Self {
+            frequency,
+            volume,
+            muted: false,
+            phase: 0.0,
+        }
+    }
+
+    // Fill out with samples in -1.0 - 1.0, silence unless on
+    pub fn fill(&mut self, out: &mut [f32], sample_rate: u32, on: bool) {
+        let step = self.frequency / sample_rate as f32;
+        let level = if on && !self.muted { self.volume } else { 0.0 };
+        for sample in out.iter_mut() {
+            *sample = if self.phase < 0.5 { level } else { -level };
+            self.phase = (self.phase + step) % 1.0;
+        }
+    }
+}
+
+impl Default for SquareWave {
+    fn default() -> Self {
+        Self::new(DEFAULT_FREQUENCY, DEFAULT_VOLUME)
+    }
+}
+
+// 16 bit mono PCM WAV writer. The header sizes are patched in by finish()
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut out: W, sample_rate: u32) -> io::Result<Self> {
+        out.write_all(b"RIFF")?;
+        out.write_all(&36u32.to_le_bytes())?;              // RIFF size, patched later
+        out.write_all(b"WAVE")?;
+        out.write_all(b"fmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;              // fmt chunk size
+        out.write_all(&1u16.to_le_bytes())?;               // PCM
+        out.write_all(&1u16.to_le_bytes())?;               // Mono
+        out.write_all(&sample_rate.to_le_bytes())?;
+        out.write_all(&(sample_rate * 2).to_le_bytes())?;  // Byte rate
+        out.write_all(&2u16.to_le_bytes())?;               // Block align
+        out.write_all(&16u16.to_le_bytes())?;              // Bits per sample
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;               // Data size, patched later
+        Ok(Self { out, samples: 0 })
+    }
+
+    // Append samples in -1.0 - 1.0
+    pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            let pcm = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.out.write_all(&pcm.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    // Patch the header and hand back the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let data_size = self.samples * 2;
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&(36 + data_size).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(40))?;
+        self.out.write_all(&data_size.to_le_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
@@ -0,0 +1,106 @@
+/*
+    Clock : paces emulation by real time instead of by the display's refresh rate
+
+    Frontends call advance with the time since their last call and get back how many 60Hz
+    frames are due. Each frame ticks the timers once and runs frame_ticks() instructions, which
+    spreads the target instructions per second evenly over the frames even when it isn't a
+    multiple of 60. A 144Hz display then sees a frame on some refreshes and none on others,
+    while the machine keeps running at the same speed.
+
+        let frames = clock.advance(now - last);
+        for _ in 0..frames {
+            for _ in 0..clock.frame_ticks() { emu.tick()?; }
+            emu.tick_timers();
+        }
+ */
+use std::time::Duration;
+
+pub const TIMER_HZ: u32 = 60;
+pub const DEFAULT_IPS: u32 = 600;       // 10 instructions a frame, what the frontends always ran
+pub const MIN_IPS: u32 = TIMER_HZ;      // One instruction a frame
+pub const MAX_IPS: u32 = 1_000_000;
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / TIMER_HZ as u64);
+
+// Most time one advance makes up for. Past this, after a stall like a dragged window or a
+// breakpoint, the machine picks up where it was instead of racing to catch up
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
+pub struct Clock {
+    ips: u32,
+    pub paused: bool,
+    pending: Duration,      // Real time not yet turned into frames
+    carry: u32,             // Instructions owed to later frames, in 60ths
+    step: bool,             // Frame advance asked for while paused
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(DEFAULT_IPS)
+    }
+}
+
+impl Clock {
+    pub fn new(ips: u32) -> Self {
+        Self {
+            ips: ips.clamp(MIN_IPS, MAX_IPS),
+            paused: false,
+            pending: Duration::ZERO,
+            carry: 0,
+            step: false,
+        }
+    }
+
+    // Target instructions per second
+    pub fn ips(&self) -> u32 {
+        self.ips
+    }
+
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips.clamp(MIN_IPS, MAX_IPS);
+        self.carry = 0;
+    }
+
+    // Double or halve the speed, returns the new target
+    pub fn faster(&mut self) -> u32 {
+        self.set_ips(self.ips.saturating_mul(2));
+        self.ips
+    }
+
+    pub fn slower(&mut self) -> u32 {
+        self.set_ips(self.ips / 2);
+        self.ips
+    }
+
+    // Instructions every frame, None when frames don't all get the same number
+    pub fn ticks_per_frame(&self) -> Option<usize> {
+        self.ips.is_multiple_of(TIMER_HZ).then_some((self.ips / TIMER_HZ) as usize)
+    }
+
+    // Frames due after elapsed more real time. Time spent paused is dropped, apart from a
+    // single frame after frame_advance
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        if self.paused {
+            self.pending = Duration::ZERO;
+            return std::mem::take(&mut self.step) as u32;
+        }
+        self.pending = (self.pending + elapsed).min(MAX_LAG);
+        let frames = (self.pending.as_nanos() / FRAME.as_nanos()) as u32;
+        self.pending -= FRAME * frames;
+        frames
+    }
+
+    // Run one frame on the next advance, while paused
+    pub fn frame_advance(&mut self) {
+        self.step = self.paused;
+    }
+
+    // Instructions to run in the next frame
+    pub fn frame_ticks(&mut self) -> usize {
+        self.carry += self.ips;
+        let ticks = self.carry / TIMER_HZ;
+        self.carry %= TIMER_HZ;
+        ticks as usize
+    }
+}
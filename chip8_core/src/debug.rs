@@ -0,0 +1,332 @@
+/*
+    Debugger : breakpoints, stepping and register access for frontends
+
+    Breakpoints stop run / run_until before the instruction they name executes. Running again
+    from where it stopped executes that instruction instead of stopping on it a second time.
+
+    Watchpoints cover an address range and stop after the instruction that read, wrote or
+    fetched a byte in it, reporting that instruction's pc and opcode.
+ */
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::{Emu, EmuError};
+
+// Opcode with wildcard nibbles, an opcode matches when op & mask == value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpcodePattern {
+    pub value: u16,
+    pub mask: u16,
+}
+
+impl OpcodePattern {
+    // Four characters like "8XY4" or "DXYN". Hex digits have to match, X, Y, N and ? match anything
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.chars().count() != 4 {
+            return None;
+        }
+        let mut pattern = OpcodePattern { value: 0, mask: 0 };
+        for c in text.chars() {
+            pattern.value <<= 4;
+            pattern.mask <<= 4;
+            match c.to_ascii_uppercase() {
+                'X' | 'Y' | 'N' | '?' => (),
+                c => {
+                    pattern.value |= c.to_digit(16)? as u16;
+                    pattern.mask |= 0xF;
+                },
+            }
+        }
+        Some(pattern)
+    }
+
+    pub fn matches(&self, op: u16) -> bool {
+        op & self.mask == self.value
+    }
+}
+
+impl fmt::Display for OpcodePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for shift in [12, 8, 4, 0] {
+            if (self.mask >> shift) & 0xF == 0 {
+                write!(f, "?")?;
+            } else {
+                write!(f, "{:X}", (self.value >> shift) & 0xF)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Kind of RAM access a watchpoint looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,    // Opcode fetch
+    ReadWrite,  // Either a read or a write, only for watching
+}
+
+impl Access {
+    // Whether a watchpoint for self stops on access
+    fn covers(self, access: Access) -> bool {
+        self == access || (self == Access::ReadWrite && matches!(access, Access::Read | Access::Write))
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "write"),
+            Access::Execute => write!(f, "execute"),
+            Access::ReadWrite => write!(f, "access"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub access: Access,
+}
+
+// Why run / run_until handed control back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Done,                           // Every step asked for ran
+    Reached,                        // run_until got to its address
+    Breakpoint { pc: u16 },         // Address breakpoint
+    Pattern { pc: u16, op: u16 },   // Opcode breakpoint
+    Watchpoint { pc: u16, op: u16, addr: u16, access: Access, watch: Access },    // Instruction at pc touched addr, watch is the watchpoint's kind
+    Exited,                         // 00FD
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Done => write!(f, "done"),
+            Stop::Reached => write!(f, "reached target"),
+            Stop::Breakpoint { pc } => write!(f, "breakpoint at {:#05X}", pc),
+            Stop::Pattern { pc, op } => write!(f, "opcode breakpoint on {:#06X} at {:#05X}", op, pc),
+            Stop::Watchpoint { pc, op, addr, access, watch } if access == watch => {
+                write!(f, "{} watchpoint on {:#05X} by {:#06X} at {:#05X}", access, addr, op, pc)
+            },
+            Stop::Watchpoint { pc, op, addr, access, watch } => {
+                write!(f, "{} watchpoint on {:#05X}, {} by {:#06X} at {:#05X}", watch, addr, access, op, pc)
+            },
+            Stop::Exited => write!(f, "exited"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Breakpoints {
+    addrs: BTreeSet<u16>,
+    patterns: Vec<OpcodePattern>,
+    resume: Option<u16>,    // pc the last run stopped at, the next run doesn't stop there again
+    watchpoints: Vec<Watchpoint>,
+    hit: Option<(u16, Access, Access)>,     // First watched access of the current instruction and the watchpoint's kind
+}
+
+impl Breakpoints {
+    // Called for every RAM access an instruction makes
+    pub(crate) fn watch(&mut self, addr: usize, access: Access) {
+        if self.hit.is_some() || self.watchpoints.is_empty() {
+            return;
+        }
+        let addr = addr as u16;
+        if let Some(w) = self.watchpoints.iter().find(|w| w.access.covers(access) && w.range.contains(&addr)) {
+            self.hit = Some((addr, access, w.access));
+        }
+    }
+}
+
+impl Emu {
+    // Execute one instruction, breakpoints don't apply
+    pub fn step(&mut self) -> Result<(), EmuError> {
+        self.debug.resume = None;
+        self.debug.hit = None;
+        self.tick()
+    }
+
+    // Execute up to steps instructions, stopping early at a breakpoint, watchpoint or exit
+    pub fn run(&mut self, steps: usize) -> Result<Stop, EmuError> {
+        self.run_to(steps, None)
+    }
+
+    // Like run, but also stops before executing the instruction at pc
+    pub fn run_until(&mut self, pc: u16, max_steps: usize) -> Result<Stop, EmuError> {
+        self.run_to(max_steps, Some(pc))
+    }
+
+    fn run_to(&mut self, steps: usize, target: Option<u16>) -> Result<Stop, EmuError> {
+        for _ in 0..steps {
+            if self.exited {
+                return Ok(Stop::Exited);
+            }
+            if let Some(stop) = self.breakpoint_hit(target) {
+                self.debug.resume = Some(self.pc);
+                return Ok(stop);
+            }
+            self.debug.resume = None;
+
+            let pc = self.pc;
+            let op = self.peek_opcode(pc).unwrap_or(0);
+            self.debug.hit = None;
+            self.tick()?;
+            if let Some((addr, access, watch)) = self.debug.hit.take() {
+                return Ok(Stop::Watchpoint { pc, op, addr, access, watch });
+            }
+        }
+        Ok(Stop::Done)
+    }
+
+    fn breakpoint_hit(&self, target: Option<u16>) -> Option<Stop> {
+        let pc = self.pc;
+        if self.debug.resume == Some(pc) {
+            return None;
+        }
+        if target == Some(pc) {
+            return Some(Stop::Reached);
+        }
+        if self.debug.addrs.contains(&pc) {
+            return Some(Stop::Breakpoint { pc });
+        }
+        if !self.debug.patterns.is_empty() {
+            let op = self.peek_opcode(pc)?;
+            if self.debug.patterns.iter().any(|p| p.matches(op)) {
+                return Some(Stop::Pattern { pc, op });
+            }
+        }
+        None
+    }
+
+    // Opcode at addr without fetching it, None past the end of RAM
+    pub fn peek_opcode(&self, addr: u16) -> Option<u16> {
+        let hi = *self.ram.get(addr as usize)?;
+        let lo = *self.ram.get(addr as usize + 1)?;
+        Some(u16::from_be_bytes([hi, lo]))
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.debug.addrs.insert(addr);
+    }
+
+    // Returns whether there was a breakpoint at addr
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.debug.addrs.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.debug.addrs.iter().copied()
+    }
+
+    pub fn add_opcode_breakpoint(&mut self, pattern: OpcodePattern) {
+        if !self.debug.patterns.contains(&pattern) {
+            self.debug.patterns.push(pattern);
+        }
+    }
+
+    // Returns whether the pattern was set
+    pub fn remove_opcode_breakpoint(&mut self, pattern: OpcodePattern) -> bool {
+        let before = self.debug.patterns.len();
+        self.debug.patterns.retain(|p| *p != pattern);
+        self.debug.patterns.len() != before
+    }
+
+    pub fn opcode_breakpoints(&self) -> &[OpcodePattern] {
+        &self.debug.patterns
+    }
+
+    // Watch range for one kind of access
+    pub fn add_watchpoint(&mut self, range: RangeInclusive<u16>, access: Access) {
+        let watchpoint = Watchpoint { range, access };
+        if !self.debug.watchpoints.contains(&watchpoint) {
+            self.debug.watchpoints.push(watchpoint);
+        }
+    }
+
+    // Returns whether the watchpoint was set
+    pub fn remove_watchpoint(&mut self, range: RangeInclusive<u16>, access: Access) -> bool {
+        let watchpoint = Watchpoint { range, access };
+        let before = self.debug.watchpoints.len();
+        self.debug.watchpoints.retain(|w| *w != watchpoint);
+        self.debug.watchpoints.len() != before
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.debug.watchpoints
+    }
+
+    // Removes watchpoints too
+    pub fn clear_breakpoints(&mut self) {
+        self.debug.addrs.clear();
+        self.debug.patterns.clear();
+        self.debug.watchpoints.clear();
+    }
+
+    // Machine state
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    // V0 - VF
+    pub fn registers(&self) -> &[u8] {
+        &self.v_reg
+    }
+
+    // x is the register number, 0x0 - 0xF, anything past VF is ignored
+    pub fn set_register(&mut self, x: usize, value: u8) {
+        if let Some(reg) = self.v_reg.get_mut(x) {
+            *reg = value;
+        }
+    }
+
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    pub fn set_i_reg(&mut self, value: u16) {
+        self.i_reg = value;
+    }
+
+    // Return addresses, innermost call last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.dt = value;
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.st = value;
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    // Overwrite RAM starting at addr
+    pub fn write_ram(&mut self, addr: u16, data: &[u8]) -> Result<(), EmuError> {
+        let addr = addr as usize;
+        self.check_ram(addr, data.len())?;
+        self.ram[addr..addr + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
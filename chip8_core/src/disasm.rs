@@ -0,0 +1,253 @@
+/*
+    Disassembler : ROM bytes -> listing
+
+    Linear sweep from the start address. Words that aren't instructions for the chosen mode
+    come out as data (DW / DB in Cowgod syntax, bare bytes in Octo syntax).
+ */
+use std::fmt;
+
+use crate::{decode, Instruction, Mode};
+
+// Output flavour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    #[default]
+    Cowgod,     // Cowgod's technical reference: LD V3, 0x2A
+    Octo,       // Octo statements: v3 := 0x2A
+}
+
+impl Syntax {
+    // cowgod or octo, any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "cowgod" => Some(Syntax::Cowgod),
+            "octo" => Some(Syntax::Octo),
+            _ => None,
+        }
+    }
+}
+
+// One line of the listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub addr: u16,
+    pub bytes: Vec<u8>,         // 2 bytes, 4 for XO-CHIP F000 NNNN, 1 for a trailing data byte
+    pub instruction: Option<Instruction>,   // None for data
+    pub mnemonic: String,       // In Octo syntax this is the statement's first token
+    pub operands: Vec<String>,
+    pub syntax: Syntax,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = match self.syntax {
+            Syntax::Cowgod => ", ",
+            Syntax::Octo => " ",
+        };
+        write!(f, "{}", self.mnemonic)?;
+        if !self.operands.is_empty() {
+            write!(f, " {}", self.operands.join(separator))?;
+        }
+        Ok(())
+    }
+}
+
+// Disassemble rom as if it was loaded at start
+pub fn disassemble(rom: &[u8], start: u16, mode: Mode, syntax: Syntax) -> Vec<Line> {
+    let mut listing = Vec::new();
+    let mut pos = 0;
+    while pos < rom.len() {
+        let addr = start.wrapping_add(pos as u16);
+
+        // A lone byte at the end can only be data
+        if pos + 1 == rom.len() {
+            let (mnemonic, operands) = data_byte(rom[pos], syntax);
+            listing.push(line(addr, &rom[pos..], None, mnemonic, operands, syntax));
+            break;
+        }
+
+        let op = u16::from_be_bytes([rom[pos], rom[pos + 1]]);
+        let instr = decode(op).ok().filter(|instr| instr.mode() <= mode && !matches!(instr, Instruction::Sys { .. }));
+
+        let (instr, len) = match instr {
+            // XO-CHIP F000 NNNN carries its address in the following word
+            Some(Instruction::LoadILong) if pos + 3 >= rom.len() => (None, 2),
+            Some(instr) => (Some(instr), instr.size() as usize),
+            None => (None, 2),
+        };
+        let next = if len == 4 { u16::from_be_bytes([rom[pos + 2], rom[pos + 3]]) } else { 0 };
+
+        let (mnemonic, operands) = match instr {
+            Some(instr) => format(instr, next, syntax),
+            None => data_word(op, syntax),
+        };
+        listing.push(line(addr, &rom[pos..pos + len], instr, mnemonic, operands, syntax));
+        pos += len;
+    }
+    listing
+}
+
+// Text for a single instruction, next is the word after it (only F000 NNNN reads it)
+pub fn format_instruction(instr: Instruction, next: u16, syntax: Syntax) -> String {
+    let (mnemonic, operands) = format(instr, next, syntax);
+    line(0, &[], Some(instr), mnemonic, operands, syntax).to_string()
+}
+
+fn format(instr: Instruction, next: u16, syntax: Syntax) -> (String, Vec<String>) {
+    match syntax {
+        Syntax::Cowgod => cowgod(instr, next),
+        Syntax::Octo => octo(instr, next),
+    }
+}
+
+fn line(addr: u16, bytes: &[u8], instruction: Option<Instruction>, mnemonic: String, operands: Vec<String>, syntax: Syntax) -> Line {
+    Line {
+        addr,
+        bytes: bytes.to_vec(),
+        instruction,
+        mnemonic,
+        operands,
+        syntax,
+    }
+}
+
+fn data_word(op: u16, syntax: Syntax) -> (String, Vec<String>) {
+    match syntax {
+        Syntax::Cowgod => (s("DW"), vec![format!("0x{:04X}", op)]),
+        Syntax::Octo => (format!("0x{:02X}", op >> 8), vec![format!("0x{:02X}", op & 0xFF)]),
+    }
+}
+
+fn data_byte(byte: u8, syntax: Syntax) -> (String, Vec<String>) {
+    match syntax {
+        Syntax::Cowgod => (s("DB"), vec![format!("0x{:02X}", byte)]),
+        Syntax::Octo => (format!("0x{:02X}", byte), vec![]),
+    }
+}
+
+fn s(text: &str) -> String {
+    text.to_string()
+}
+
+fn cowgod(instr: Instruction, next: u16) -> (String, Vec<String>) {
+    use Instruction::*;
+    let v = |r: u8| format!("V{:X}", r);
+    let byte = |nn: u8| format!("0x{:02X}", nn);
+    let addr = |nnn: u16| format!("0x{:03X}", nnn);
+
+    match instr {
+        Sys { nnn } => (s("SYS"), vec![addr(nnn)]),
+        Cls => (s("CLS"), vec![]),
+        Ret => (s("RET"), vec![]),
+        ScrollDown { n } => (s("SCD"), vec![n.to_string()]),
+        ScrollUp { n } => (s("SCU"), vec![n.to_string()]),
+        ScrollRight => (s("SCR"), vec![]),
+        ScrollLeft => (s("SCL"), vec![]),
+        Exit => (s("EXIT"), vec![]),
+        Lores => (s("LOW"), vec![]),
+        Hires => (s("HIGH"), vec![]),
+        Jump { nnn } => (s("JP"), vec![addr(nnn)]),
+        Call { nnn } => (s("CALL"), vec![addr(nnn)]),
+        SkipEqImm { x, nn } => (s("SE"), vec![v(x), byte(nn)]),
+        SkipNeImm { x, nn } => (s("SNE"), vec![v(x), byte(nn)]),
+        SkipEqReg { x, y } => (s("SE"), vec![v(x), v(y)]),
+        SaveRange { x, y } => (s("SAVE"), vec![v(x), v(y)]),
+        LoadRange { x, y } => (s("LOAD"), vec![v(x), v(y)]),
+        LoadImm { x, nn } => (s("LD"), vec![v(x), byte(nn)]),
+        AddImm { x, nn } => (s("ADD"), vec![v(x), byte(nn)]),
+        Move { x, y } => (s("LD"), vec![v(x), v(y)]),
+        Or { x, y } => (s("OR"), vec![v(x), v(y)]),
+        And { x, y } => (s("AND"), vec![v(x), v(y)]),
+        Xor { x, y } => (s("XOR"), vec![v(x), v(y)]),
+        Add { x, y } => (s("ADD"), vec![v(x), v(y)]),
+        Sub { x, y } => (s("SUB"), vec![v(x), v(y)]),
+        Shr { x, y } => (s("SHR"), vec![v(x), v(y)]),
+        SubN { x, y } => (s("SUBN"), vec![v(x), v(y)]),
+        Shl { x, y } => (s("SHL"), vec![v(x), v(y)]),
+        SkipNeReg { x, y } => (s("SNE"), vec![v(x), v(y)]),
+        LoadI { nnn } => (s("LD"), vec![s("I"), addr(nnn)]),
+        JumpOffset { nnn } => (s("JP"), vec![s("V0"), addr(nnn)]),
+        Random { x, nn } => (s("RND"), vec![v(x), byte(nn)]),
+        Draw { x, y, n } => (s("DRW"), vec![v(x), v(y), n.to_string()]),
+        SkipKey { x } => (s("SKP"), vec![v(x)]),
+        SkipNotKey { x } => (s("SKNP"), vec![v(x)]),
+        LoadILong => (s("LD"), vec![s("I"), format!("LONG 0x{:04X}", next)]),
+        Plane { n } => (s("PLANE"), vec![n.to_string()]),
+        Audio => (s("AUDIO"), vec![]),
+        GetDelay { x } => (s("LD"), vec![v(x), s("DT")]),
+        WaitKey { x } => (s("LD"), vec![v(x), s("K")]),
+        SetDelay { x } => (s("LD"), vec![s("DT"), v(x)]),
+        SetSound { x } => (s("LD"), vec![s("ST"), v(x)]),
+        AddI { x } => (s("ADD"), vec![s("I"), v(x)]),
+        Font { x } => (s("LD"), vec![s("F"), v(x)]),
+        BigFont { x } => (s("LD"), vec![s("HF"), v(x)]),
+        Bcd { x } => (s("LD"), vec![s("B"), v(x)]),
+        Pitch { x } => (s("PITCH"), vec![v(x)]),
+        Store { x } => (s("LD"), vec![s("[I]"), v(x)]),
+        Load { x } => (s("LD"), vec![v(x), s("[I]")]),
+        SaveFlags { x } => (s("LD"), vec![s("R"), v(x)]),
+        LoadFlags { x } => (s("LD"), vec![v(x), s("R")]),
+    }
+}
+
+fn octo(instr: Instruction, next: u16) -> (String, Vec<String>) {
+    use Instruction::*;
+    let v = |r: u8| format!("v{:x}", r);
+    let byte = |nn: u8| format!("0x{:02X}", nn);
+    let addr = |nnn: u16| format!("0x{:03X}", nnn);
+
+    // Octo has no mnemonics, a statement is split into its first token and the rest
+    match instr {
+        Sys { nnn } => data_word(nnn, Syntax::Octo),
+        Cls => (s("clear"), vec![]),
+        Ret => (s("return"), vec![]),
+        ScrollDown { n } => (s("scroll-down"), vec![n.to_string()]),
+        ScrollUp { n } => (s("scroll-up"), vec![n.to_string()]),
+        ScrollRight => (s("scroll-right"), vec![]),
+        ScrollLeft => (s("scroll-left"), vec![]),
+        Exit => (s("exit"), vec![]),
+        Lores => (s("lores"), vec![]),
+        Hires => (s("hires"), vec![]),
+        Jump { nnn } => (s("jump"), vec![addr(nnn)]),
+        Call { nnn } => (s(":call"), vec![addr(nnn)]),
+        SkipEqImm { x, nn } => (s("if"), vec![v(x), s("!="), byte(nn), s("then")]),
+        SkipNeImm { x, nn } => (s("if"), vec![v(x), s("=="), byte(nn), s("then")]),
+        SkipEqReg { x, y } => (s("if"), vec![v(x), s("!="), v(y), s("then")]),
+        SaveRange { x, y } => (s("save"), vec![v(x), s("-"), v(y)]),
+        LoadRange { x, y } => (s("load"), vec![v(x), s("-"), v(y)]),
+        LoadImm { x, nn } => (v(x), vec![s(":="), byte(nn)]),
+        AddImm { x, nn } => (v(x), vec![s("+="), byte(nn)]),
+        Move { x, y } => (v(x), vec![s(":="), v(y)]),
+        Or { x, y } => (v(x), vec![s("|="), v(y)]),
+        And { x, y } => (v(x), vec![s("&="), v(y)]),
+        Xor { x, y } => (v(x), vec![s("^="), v(y)]),
+        Add { x, y } => (v(x), vec![s("+="), v(y)]),
+        Sub { x, y } => (v(x), vec![s("-="), v(y)]),
+        Shr { x, y } => (v(x), vec![s(">>="), v(y)]),
+        SubN { x, y } => (v(x), vec![s("=-"), v(y)]),
+        Shl { x, y } => (v(x), vec![s("<<="), v(y)]),
+        SkipNeReg { x, y } => (s("if"), vec![v(x), s("=="), v(y), s("then")]),
+        LoadI { nnn } => (s("i"), vec![s(":="), addr(nnn)]),
+        JumpOffset { nnn } => (s("jump0"), vec![addr(nnn)]),
+        Random { x, nn } => (v(x), vec![s(":="), s("random"), byte(nn)]),
+        Draw { x, y, n } => (s("sprite"), vec![v(x), v(y), n.to_string()]),
+        SkipKey { x } => (s("if"), vec![v(x), s("-key"), s("then")]),
+        SkipNotKey { x } => (s("if"), vec![v(x), s("key"), s("then")]),
+        LoadILong => (s("i"), vec![s(":="), s("long"), format!("0x{:04X}", next)]),
+        Plane { n } => (s("plane"), vec![n.to_string()]),
+        Audio => (s("audio"), vec![]),
+        GetDelay { x } => (v(x), vec![s(":="), s("delay")]),
+        WaitKey { x } => (v(x), vec![s(":="), s("key")]),
+        SetDelay { x } => (s("delay"), vec![s(":="), v(x)]),
+        SetSound { x } => (s("buzzer"), vec![s(":="), v(x)]),
+        AddI { x } => (s("i"), vec![s("+="), v(x)]),
+        Font { x } => (s("i"), vec![s(":="), s("hex"), v(x)]),
+        BigFont { x } => (s("i"), vec![s(":="), s("bighex"), v(x)]),
+        Bcd { x } => (s("bcd"), vec![v(x)]),
+        Pitch { x } => (s("pitch"), vec![s(":="), v(x)]),
+        Store { x } => (s("save"), vec![v(x)]),
+        Load { x } => (s("load"), vec![v(x)]),
+        SaveFlags { x } => (s("saveflags"), vec![v(x)]),
+        LoadFlags { x } => (s("loadflags"), vec![v(x)]),
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::path::PathBuf;
+
+// Errors the core can hit while loading or running a ROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmuError {
+    UnknownOpcode { pc: u16, op: u16 },         // Opcode at pc doesn't decode to anything we run
+    StackOverflow,                              // CALL with a full stack
+    StackUnderflow,                             // RET with an empty stack
+    MemoryOutOfBounds { addr: usize },          // Read or write past the end of RAM
+    InvalidKey { vx: u8 },                      // Key skip on a VX that isn't a key (0x0 - 0xF)
+    RomTooLarge { size: usize, max: usize },    // ROM doesn't fit between START_ADDR and the end of RAM
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::UnknownOpcode { pc, op } => write!(f, "unknown opcode {:#06X} at {:#05X}", op, pc),
+            EmuError::StackOverflow => write!(f, "stack overflow"),
+            EmuError::StackUnderflow => write!(f, "stack underflow"),
+            EmuError::MemoryOutOfBounds { addr } => write!(f, "memory access out of bounds at {:#X}", addr),
+            EmuError::InvalidKey { vx } => write!(f, "invalid key {:#04X}", vx),
+            EmuError::RomTooLarge { size, max } => write!(f, "ROM is {} bytes, at most {} fit in memory", size, max),
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
+
+// Errors from restoring a save state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    BadMagic,                   // Not a save state at all
+    UnsupportedVersion(u16),    // Written by a newer core, or an old format we can't migrate
+    Truncated,                  // Data ends before the state does
+    Invalid(&'static str),      // A field holds a value the core can't be in
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid(field) => write!(f, "save state has an invalid {}", field),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+// Opcode that isn't an instruction on any platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub op: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06X} is not an instruction", self.op)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// Assembler error, line and column are 1-based, the column counting bytes. Line 0 means the
+// file couldn't be read at all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: Option<PathBuf>,      // None for source passed in as a string
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(path) => write!(f, "{}", path.display())?,
+            None => write!(f, "<source>")?,
+        }
+        if self.line > 0 {
+            write!(f, ":{}:{}", self.line, self.column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// Key script error, line is 1-based
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+// Errors from reading a movie file or setting up its replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    Parse { line: usize, message: String },     // line is 1-based
+    RomMismatch { expected: String, found: String },    // SHA-1 of the ROM the movie was recorded with, and of the one given
+    Load(EmuError),                             // ROM doesn't fit the recorded mode
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            MovieError::RomMismatch { expected, found } => {
+                write!(f, "movie was recorded with ROM {}, this one is {}", expected, found)
+            },
+            MovieError::Load(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+// Errors from reading a ROM database file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseError {
+    Json { line: usize, message: String },      // Not valid JSON, line is 1-based
+    Format(String),                             // Valid JSON, but not laid out like the database
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Json { line, message } => write!(f, "line {}: {}", line, message),
+            DatabaseError::Format(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
@@ -0,0 +1,433 @@
+/*
+    GDB remote serial protocol stub
+
+    Speaks enough RSP over any byte stream for GDB, or anything scripted against RSP, to read
+    and write registers and memory, single step, continue and set breakpoints and watchpoints.
+    The frontend owns the connection and calls run_frame once per frame. Each call drains the
+    packets that arrived since the last one, so a non-blocking socket keeps the window alive.
+
+    Register numbers, also the order of a `g` reply: V0 - VF are 0 - 15, then I, PC, SP, DT
+    and ST. I and PC are 16 bit little endian, the rest are single bytes.
+ */
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::{Access, Emu, EmuError, Stop};
+
+const NUM_REGS: usize = 21;
+const REG_I: usize = 16;
+const REG_PC: usize = 17;
+const REG_SP: usize = 18;
+const REG_DT: usize = 19;
+const REG_ST: usize = 20;
+
+// Signals GDB shows for a stop
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>"#;
+
+// What the frontend should do after run_frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GdbStatus {
+    Running,    // Instructions ran this frame, finish it as usual (timers etc.)
+    Halted,     // Waiting for the debugger
+    Detached,   // Debugger left or the connection closed
+}
+
+pub struct GdbStub<S: Read + Write> {
+    stream: S,
+    input: Vec<u8>,         // Bytes received but not handled yet
+    last_reply: Vec<u8>,    // Sent again if the client NAKs it
+    running: bool,
+    no_ack: bool,           // QStartNoAckMode
+    detached: bool,
+}
+
+impl<S: Read + Write> GdbStub<S> {
+    // The machine starts out halted, as GDB expects after connecting
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            input: Vec::new(),
+            last_reply: Vec::new(),
+            running: false,
+            no_ack: false,
+            detached: false,
+        }
+    }
+
+    // Handle incoming packets, then run up to steps instructions if GDB said continue
+    pub fn run_frame(&mut self, emu: &mut Emu, steps: usize) -> io::Result<GdbStatus> {
+        self.receive()?;
+        while let Some(packet) = self.next_packet()? {
+            self.handle(emu, &packet)?;
+            if self.detached {
+                return Ok(GdbStatus::Detached);
+            }
+        }
+        if self.detached {
+            return Ok(GdbStatus::Detached);
+        }
+        if !self.running {
+            return Ok(GdbStatus::Halted);
+        }
+
+        match emu.run(steps) {
+            Ok(Stop::Done) => return Ok(GdbStatus::Running),
+            Ok(Stop::Exited) => self.send(b"W00")?,
+            Ok(Stop::Watchpoint { addr, watch, .. }) if watch != Access::Execute => {
+                let kind = match watch {
+                    Access::Write => "watch",
+                    Access::Read => "rwatch",
+                    _ => "awatch",
+                };
+                self.send(format!("T{:02x}{}:{:x};", SIGTRAP, kind, addr).as_bytes())?;
+            },
+            Ok(_) => self.send(format!("S{:02x}", SIGTRAP).as_bytes())?,
+            Err(e) => self.send(format!("S{:02x}", signal(e)).as_bytes())?,
+        }
+        self.running = false;
+        Ok(GdbStatus::Running)
+    }
+
+    // Read everything available without blocking on a non-blocking stream
+    fn receive(&mut self) -> io::Result<()> {
+        let mut buf = [0; 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    self.detached = true;
+                    return Ok(());
+                },
+                Ok(n) => {
+                    self.input.extend_from_slice(&buf[..n]);
+                    if n < buf.len() {
+                        return Ok(());
+                    }
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Next complete packet body from input. Acks, NAKs and Ctrl-C are handled along the way
+    fn next_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            match self.input.first() {
+                None => return Ok(None),
+                Some(b'+') => {
+                    self.input.remove(0);
+                },
+                Some(b'-') => {
+                    self.input.remove(0);
+                    let reply = self.last_reply.clone();
+                    self.stream.write_all(&reply)?;
+                },
+                Some(0x03) => {
+                    // Interrupt, only meaningful while running
+                    self.input.remove(0);
+                    if self.running {
+                        self.running = false;
+                        self.send(format!("S{:02x}", SIGINT).as_bytes())?;
+                    }
+                },
+                Some(b'$') => {
+                    let Some(hash) = self.input.iter().position(|&b| b == b'#') else { return Ok(None) };
+                    if self.input.len() < hash + 3 {
+                        return Ok(None);
+                    }
+                    let body = self.input[1..hash].to_vec();
+                    let checksum = std::str::from_utf8(&self.input[hash + 1..hash + 3]).ok()
+                        .and_then(|text| u8::from_str_radix(text, 16).ok());
+                    self.input.drain(..hash + 3);
+
+                    if checksum == Some(checksum_of(&body)) {
+                        if !self.no_ack {
+                            self.stream.write_all(b"+")?;
+                        }
+                        return Ok(Some(body));
+                    }
+                    self.stream.write_all(b"-")?;
+                },
+                Some(_) => {
+                    self.input.remove(0);   // Noise between packets
+                },
+            }
+        }
+    }
+
+    fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(data.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(data);
+        packet.extend_from_slice(format!("#{:02x}", checksum_of(data)).as_bytes());
+        self.stream.write_all(&packet)?;
+        self.stream.flush()?;
+        self.last_reply = packet;
+        Ok(())
+    }
+
+    fn handle(&mut self, emu: &mut Emu, packet: &[u8]) -> io::Result<()> {
+        // Split before decoding, a lossy decode can turn one stray byte into several
+        let Some((&cmd, args)) = packet.split_first() else { return self.send(b"") };
+        let args = String::from_utf8_lossy(args).into_owned();
+        let args = args.as_str();
+
+        let reply = match cmd {
+            b'?' => format!("S{:02x}", SIGTRAP),
+            b'g' => registers(emu).iter().map(|b| format!("{:02x}", b)).collect(),
+            b'G' => match hex_bytes(args).filter(|bytes| bytes.len() == registers(emu).len()) {
+                Some(bytes) => {
+                    set_registers(emu, &bytes);
+                    ok()
+                },
+                None => error(1),
+            },
+            b'p' => match usize::from_str_radix(args, 16).ok().and_then(|reg| register(emu, reg)) {
+                Some(bytes) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+                None => error(1),
+            },
+            b'P' => {
+                let parsed = args.split_once('=').and_then(|(reg, value)| {
+                    Some((usize::from_str_radix(reg, 16).ok()?, hex_bytes(value)?))
+                });
+                match parsed {
+                    Some((reg, bytes)) if set_register(emu, reg, &bytes) => ok(),
+                    _ => error(1),
+                }
+            },
+            b'm' => match parse_addr_len(args) {
+                Some((addr, len)) => match addr.checked_add(len).and_then(|end| emu.ram().get(addr..end)) {
+                    Some(bytes) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+                    None => error(14),
+                },
+                None => error(1),
+            },
+            b'M' => {
+                let parsed = args.split_once(':').and_then(|(range, data)| {
+                    Some((parse_addr_len(range)?, hex_bytes(data)?))
+                });
+                match parsed {
+                    Some(((addr, len), data)) if data.len() == len && addr <= u16::MAX as usize => {
+                        match emu.write_ram(addr as u16, &data) {
+                            Ok(()) => ok(),
+                            Err(_) => error(14),
+                        }
+                    },
+                    _ => error(1),
+                }
+            },
+            b'c' => match resume_at(emu, args) {
+                Some(()) => {
+                    self.running = true;
+                    return Ok(());
+                },
+                None => error(1),
+            },
+            b's' => match resume_at(emu, args).map(|()| emu.step()) {
+                Some(Ok(())) if emu.has_exited() => s("W00"),
+                Some(Ok(())) => format!("S{:02x}", SIGTRAP),
+                Some(Err(e)) => format!("S{:02x}", signal(e)),
+                None => error(1),
+            },
+            b'Z' | b'z' => self.breakpoint(emu, cmd == b'Z', args),
+            b'D' => {
+                self.send(b"OK")?;
+                self.detached = true;
+                return Ok(());
+            },
+            b'k' => {
+                self.detached = true;
+                return Ok(());
+            },
+            b'H' => ok(),
+            b'q' | b'Q' | b'v' => self.query(&format!("{}{}", cmd as char, args)),
+            _ if !cmd.is_ascii() => error(1),
+            _ => String::new(),     // Empty reply means unsupported
+        };
+        self.send(reply.as_bytes())
+    }
+
+    // Z0 / Z1 breakpoints, Z2 write, Z3 read and Z4 access watchpoints
+    fn breakpoint(&mut self, emu: &mut Emu, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        let (Some(kind), Some(addr)) = (fields.next(), fields.next().and_then(parse_hex)) else {
+            return error(1);
+        };
+        let len = fields.next().and_then(parse_hex).unwrap_or(1).max(1);
+        let (Ok(addr), Ok(len)) = (u16::try_from(addr), u16::try_from(len)) else {
+            return error(1);
+        };
+        let range = addr..=addr.saturating_add(len - 1);
+
+        let access = match kind {
+            "0" | "1" => {
+                if insert {
+                    emu.add_breakpoint(addr);
+                } else {
+                    emu.remove_breakpoint(addr);
+                }
+                return ok();
+            },
+            "2" => Access::Write,
+            "3" => Access::Read,
+            "4" => Access::ReadWrite,
+            _ => return String::new(),
+        };
+        if insert {
+            emu.add_watchpoint(range, access);
+        } else {
+            emu.remove_watchpoint(range, access);
+        }
+        ok()
+    }
+
+    fn query(&mut self, text: &str) -> String {
+        if text.starts_with("qSupported") {
+            return s("PacketSize=4000;qXfer:features:read+;QStartNoAckMode+");
+        }
+        if let Some(rest) = text.strip_prefix("qXfer:features:read:target.xml:") {
+            // Reply with the requested window of the document, `l` marks the last chunk
+            let Some((offset, len)) = parse_addr_len(rest) else { return error(1) };
+            let start = offset.min(TARGET_XML.len());
+            let end = offset.saturating_add(len).min(TARGET_XML.len());
+            let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+            return format!("{}{}", marker, &TARGET_XML[start..end]);
+        }
+        match text {
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                ok()
+            },
+            "qAttached" => s("1"),
+            "qC" => s("QC1"),
+            "qfThreadInfo" => s("m1"),
+            "qsThreadInfo" => s("l"),
+            _ => String::new(),
+        }
+    }
+}
+
+fn registers(emu: &Emu) -> Vec<u8> {
+    (0..NUM_REGS).flat_map(|reg| register(emu, reg).unwrap_or_default()).collect()
+}
+
+fn register(emu: &Emu, reg: usize) -> Option<Vec<u8>> {
+    let value = match reg {
+        0..=15 => vec![emu.registers()[reg]],
+        REG_I => emu.i_reg().to_le_bytes().to_vec(),
+        REG_PC => emu.pc().to_le_bytes().to_vec(),
+        REG_SP => vec![emu.stack().len() as u8],
+        REG_DT => vec![emu.delay_timer()],
+        REG_ST => vec![emu.sound_timer()],
+        _ => return None,
+    };
+    Some(value)
+}
+
+// SP can't be written, a G packet leaves it alone
+fn set_registers(emu: &mut Emu, bytes: &[u8]) {
+    let mut pos = 0;
+    for reg in 0..NUM_REGS {
+        let len = register(emu, reg).map_or(0, |value| value.len());
+        if reg != REG_SP {
+            set_register(emu, reg, &bytes[pos..pos + len]);
+        }
+        pos += len;
+    }
+}
+
+fn set_register(emu: &mut Emu, reg: usize, bytes: &[u8]) -> bool {
+    match (reg, bytes) {
+        (0..=15, [value]) => emu.set_register(reg, *value),
+        (REG_I, [lo, hi]) => emu.set_i_reg(u16::from_le_bytes([*lo, *hi])),
+        (REG_PC, [lo, hi]) => emu.set_pc(u16::from_le_bytes([*lo, *hi])),
+        (REG_DT, [value]) => emu.set_delay_timer(*value),
+        (REG_ST, [value]) => emu.set_sound_timer(*value),
+        _ => return false,
+    }
+    true
+}
+
+fn signal(e: EmuError) -> u8 {
+    match e {
+        EmuError::UnknownOpcode { .. } => SIGILL,
+        EmuError::MemoryOutOfBounds { .. } | EmuError::StackOverflow | EmuError::StackUnderflow => SIGSEGV,
+        _ => SIGTRAP,
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+// Optional resume address of c and s, None if it isn't one
+fn resume_at(emu: &mut Emu, args: &str) -> Option<()> {
+    if !args.is_empty() {
+        let addr = parse_hex(args).and_then(|addr| u16::try_from(addr).ok())?;
+        emu.set_pc(addr);
+    }
+    Some(())
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+// "addr,len" in hex
+fn parse_addr_len(text: &str) -> Option<(usize, usize)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn s(text: &str) -> String {
+    text.to_string()
+}
+
+fn ok() -> String {
+    s("OK")
+}
+
+fn error(code: u8) -> String {
+    format!("E{:02x}", code)
+}
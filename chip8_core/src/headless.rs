@@ -0,0 +1,245 @@
+/*
+    Headless runner : run a ROM without a window, for CI and batch testing
+
+    A Runner plays a key script against the emulator for up to a number of frames, checking
+    pass and fail conditions after every instruction. Frames run ticks_per_frame instructions
+    and then tick the timers once, the same as the desktop frontend.
+
+    Key scripts are one event per line, FRAME KEY down|up, with the key in hex:
+
+        # Press 5 on frame 30 and let go 3 frames later
+        30 5 down
+        33 5 up
+
+    Events for a frame are applied before that frame runs. For image dumps see screenshot.
+ */
+use std::fmt;
+
+use crate::{Emu, EmuError, ScriptError};
+
+pub const TICKS_PER_FRAME: usize = 10;
+pub const DEFAULT_FRAMES: u64 = 600;    // 10 seconds at 60Hz
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u64,
+    pub key: u8,        // 0x0 - 0xF
+    pub pressed: bool,
+}
+
+// Parse a key script, events come back sorted by frame
+pub fn parse_script(text: &str) -> Result<Vec<KeyEvent>, ScriptError> {
+    let mut events = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        if line.trim().is_empty() {
+            continue;
+        }
+        events.push(parse_event(line).map_err(|message| ScriptError { line: index + 1, message })?);
+    }
+    events.sort_by_key(|e| e.frame);     // Stable, so events on the same frame keep their order
+    Ok(events)
+}
+
+// One FRAME KEY down|up line, shared with movie files
+pub(crate) fn parse_event(line: &str) -> Result<KeyEvent, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let [frame, key, action] = words.as_slice() else {
+        return Err("expected FRAME KEY down|up".to_string());
+    };
+    let frame = frame.parse().map_err(|_| format!("bad frame number: {}", frame))?;
+    let key = u8::from_str_radix(key, 16).ok()
+        .filter(|k| *k < 16)
+        .ok_or_else(|| format!("bad key: {} (expected 0 - F)", key))?;
+    let pressed = match action.to_ascii_lowercase().as_str() {
+        "down" => true,
+        "up" => false,
+        _ => return Err(format!("bad action: {} (expected down or up)", action)),
+    };
+    Ok(KeyEvent { frame, key, pressed })
+}
+
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:X} {}", self.frame, self.key, if self.pressed { "down" } else { "up" })
+    }
+}
+
+// Machine state a run waits for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Exited,                         // 00FD ran
+    Pc(u16),                        // About to execute the instruction at this address
+    Register { x: u8, value: u8 },  // VX holds value
+    Memory { addr: u16, value: u8 },
+}
+
+impl Condition {
+    // "exit", "pc=ADDR", "vX=NN" or "mem[ADDR]=NN", numbers in hex
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        if text == "exit" {
+            return Some(Condition::Exited);
+        }
+        let (target, value) = text.split_once('=')?;
+        if target == "pc" {
+            return Some(Condition::Pc(hex(value)?));
+        }
+        if let Some(addr) = target.strip_prefix("mem[").and_then(|t| t.strip_suffix(']')) {
+            return Some(Condition::Memory { addr: hex(addr)?, value: hex(value)? });
+        }
+        let x = target.strip_prefix('v').filter(|x| x.len() == 1)?;
+        Some(Condition::Register { x: hex(x)?, value: hex(value)? })
+    }
+
+    pub fn is_met(&self, emu: &Emu) -> bool {
+        match *self {
+            Condition::Exited => emu.has_exited(),
+            Condition::Pc(pc) => emu.pc() == pc,
+            Condition::Register { x, value } => emu.registers()[x as usize] == value,
+            Condition::Memory { addr, value } => emu.ram().get(addr as usize) == Some(&value),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Exited => write!(f, "exit"),
+            Condition::Pc(pc) => write!(f, "pc={:03X}", pc),
+            Condition::Register { x, value } => write!(f, "v{:X}={:02X}", x, value),
+            Condition::Memory { addr, value } => write!(f, "mem[{:03X}]={:02X}", addr, value),
+        }
+    }
+}
+
+// How a run ended. frame is the frame it ended on, counting from 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed { frame: u64, condition: Option<Condition> },    // None when every frame ran and nothing was waited for
+    Failed { frame: u64, condition: Condition },
+    TimedOut { frames: u64 },                               // Ran out of frames waiting for a pass condition
+    Error { frame: u64, error: EmuError },
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        matches!(self, Outcome::Passed { .. })
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Passed { frame, condition: Some(c) } => write!(f, "passed: {} on frame {}", c, frame),
+            Outcome::Passed { frame, condition: None } => write!(f, "passed: ran {} frames", frame + 1),
+            Outcome::Failed { frame, condition } => write!(f, "failed: {} on frame {}", condition, frame),
+            Outcome::TimedOut { frames } => write!(f, "failed: no pass condition after {} frames", frames),
+            Outcome::Error { frame, error } => write!(f, "error on frame {}: {}", frame, error),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Runner {
+    pub frames: u64,                // Most frames to run
+    pub ticks_per_frame: usize,
+    pub script: Vec<KeyEvent>,
+    pub pass: Vec<Condition>,       // Any of these ends the run as a pass
+    pub fail: Vec<Condition>,       // Any of these ends the run as a failure, checked first
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self {
+            frames: DEFAULT_FRAMES,
+            ticks_per_frame: TICKS_PER_FRAME,
+            script: Vec::new(),
+            pass: Vec::new(),
+            fail: Vec::new(),
+        }
+    }
+}
+
+impl Runner {
+    // Run a loaded emulator until a condition is met or the frames run out
+    pub fn run(&self, emu: &mut Emu) -> Outcome {
+        self.run_with(emu, |_| ())
+    }
+
+    // Like run, calling on_frame after every frame's timer tick, and once more on the
+    // frame a run ends early on. Frontends record video from here
+    pub fn run_with<F: FnMut(&Emu)>(&self, emu: &mut Emu, mut on_frame: F) -> Outcome {
+        let mut events = self.script.iter().peekable();
+        for frame in 0..self.frames {
+            while let Some(event) = events.next_if(|e| e.frame <= frame) {
+                emu.keypress(event.key as usize, event.pressed);
+            }
+            if let Some(outcome) = self.run_frame(emu, frame) {
+                on_frame(emu);
+                return outcome;
+            }
+            emu.tick_timers();
+            on_frame(emu);
+        }
+        if self.pass.is_empty() {
+            Outcome::Passed { frame: self.frames.saturating_sub(1), condition: None }
+        } else {
+            Outcome::TimedOut { frames: self.frames }
+        }
+    }
+
+    // One frame of instructions, Some when the run is over
+    fn run_frame(&self, emu: &mut Emu, frame: u64) -> Option<Outcome> {
+        for _ in 0..self.ticks_per_frame {
+            if let Some(outcome) = self.check(emu, frame) {
+                return Some(outcome);
+            }
+            if let Err(error) = emu.tick() {
+                return Some(Outcome::Error { frame, error });
+            }
+        }
+        self.check(emu, frame)
+    }
+
+    fn check(&self, emu: &Emu, frame: u64) -> Option<Outcome> {
+        if let Some(&condition) = self.fail.iter().find(|c| c.is_met(emu)) {
+            return Some(Outcome::Failed { frame, condition });
+        }
+        if let Some(&condition) = self.pass.iter().find(|c| c.is_met(emu)) {
+            return Some(Outcome::Passed { frame, condition: Some(condition) });
+        }
+        // Nothing more will happen, which is only a pass if nothing else was waited for
+        if emu.has_exited() {
+            return Some(if self.pass.is_empty() {
+                Outcome::Passed { frame, condition: Some(Condition::Exited) }
+            } else {
+                Outcome::Failed { frame, condition: Condition::Exited }
+            });
+        }
+        None
+    }
+}
+
+// Screen as text, one line per row. Off pixels are '.', plane 1 is '#' and other
+// XO-CHIP plane combinations are their mask in hex
+pub fn ascii(emu: &Emu) -> String {
+    let width = emu.display_width();
+    let mut text = String::with_capacity((width + 1) * emu.display_height());
+    for row in emu.get_display().chunks(width) {
+        for &px in row {
+            text.push(match px {
+                0 => '.',
+                1 => '#',
+                mask => char::from_digit(mask as u32, 16).unwrap_or('?'),
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn hex<T: TryFrom<u32>>(text: &str) -> Option<T> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()?.try_into().ok()
+}
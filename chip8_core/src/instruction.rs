@@ -0,0 +1,220 @@
+/*
+    Instruction set : opcode <-> Instruction
+
+    decode knows every opcode of every platform, Instruction::mode tells which platform
+    an instruction needs. X and Y are register indices, N / NN / NNN are the immediates.
+ */
+use crate::{DecodeError, Mode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Sys { nnn: u16 },                   // 0NNN machine code routine, only 0000 runs (as a no-op)
+    Cls,                                // 00E0
+    Ret,                                // 00EE
+    ScrollDown { n: u8 },               // 00CN  SUPER-CHIP
+    ScrollUp { n: u8 },                 // 00DN  XO-CHIP
+    ScrollRight,                        // 00FB  SUPER-CHIP
+    ScrollLeft,                         // 00FC  SUPER-CHIP
+    Exit,                               // 00FD  SUPER-CHIP
+    Lores,                              // 00FE  SUPER-CHIP
+    Hires,                              // 00FF  SUPER-CHIP
+    Jump { nnn: u16 },                  // 1NNN
+    Call { nnn: u16 },                  // 2NNN
+    SkipEqImm { x: u8, nn: u8 },        // 3XNN
+    SkipNeImm { x: u8, nn: u8 },        // 4XNN
+    SkipEqReg { x: u8, y: u8 },         // 5XY0
+    SaveRange { x: u8, y: u8 },         // 5XY2  XO-CHIP
+    LoadRange { x: u8, y: u8 },         // 5XY3  XO-CHIP
+    LoadImm { x: u8, nn: u8 },          // 6XNN
+    AddImm { x: u8, nn: u8 },           // 7XNN
+    Move { x: u8, y: u8 },              // 8XY0
+    Or { x: u8, y: u8 },                // 8XY1
+    And { x: u8, y: u8 },               // 8XY2
+    Xor { x: u8, y: u8 },               // 8XY3
+    Add { x: u8, y: u8 },               // 8XY4
+    Sub { x: u8, y: u8 },               // 8XY5
+    Shr { x: u8, y: u8 },               // 8XY6
+    SubN { x: u8, y: u8 },              // 8XY7
+    Shl { x: u8, y: u8 },               // 8XYE
+    SkipNeReg { x: u8, y: u8 },         // 9XY0
+    LoadI { nnn: u16 },                 // ANNN
+    JumpOffset { nnn: u16 },            // BNNN, the jump quirk reads the top nibble as X
+    Random { x: u8, nn: u8 },           // CXNN
+    Draw { x: u8, y: u8, n: u8 },       // DXYN
+    SkipKey { x: u8 },                  // EX9E
+    SkipNotKey { x: u8 },               // EXA1
+    LoadILong,                          // F000 NNNN  XO-CHIP, the address is the next word
+    Plane { n: u8 },                    // FN01  XO-CHIP
+    Audio,                              // F002  XO-CHIP
+    GetDelay { x: u8 },                 // FX07
+    WaitKey { x: u8 },                  // FX0A
+    SetDelay { x: u8 },                 // FX15
+    SetSound { x: u8 },                 // FX18
+    AddI { x: u8 },                     // FX1E
+    Font { x: u8 },                     // FX29
+    BigFont { x: u8 },                  // FX30  SUPER-CHIP
+    Bcd { x: u8 },                      // FX33
+    Pitch { x: u8 },                    // FX3A  XO-CHIP
+    Store { x: u8 },                    // FX55
+    Load { x: u8 },                     // FX65
+    SaveFlags { x: u8 },                // FX75  SUPER-CHIP
+    LoadFlags { x: u8 },                // FX85  SUPER-CHIP
+}
+
+impl Instruction {
+    // First platform that has this instruction
+    pub fn mode(&self) -> Mode {
+        use Instruction::*;
+        match self {
+            ScrollDown { .. } | ScrollRight | ScrollLeft | Exit | Lores | Hires
+            | BigFont { .. } | SaveFlags { .. } | LoadFlags { .. } => Mode::SuperChip,
+            ScrollUp { .. } | SaveRange { .. } | LoadRange { .. } | LoadILong
+            | Plane { .. } | Audio | Pitch { .. } => Mode::XoChip,
+            _ => Mode::Chip8,
+        }
+    }
+
+    // Bytes the instruction takes in memory
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LoadILong => 4,
+            _ => 2,
+        }
+    }
+}
+
+// Opcode -> Instruction
+pub fn decode(op: u16) -> Result<Instruction, DecodeError> {
+    use Instruction::*;
+
+    // Separate each digit of opcode
+    let digit1 = (op & 0xF000) >> 12;
+    let digit2 = (op & 0x0F00) >> 8;
+    let digit3 = (op & 0x00F0) >> 4;
+    let digit4 = op & 0x000F;
+
+    let x = digit2 as u8;
+    let y = digit3 as u8;
+    let n = digit4 as u8;
+    let nn = (op & 0xFF) as u8;
+    let nnn = op & 0xFFF;
+
+    let instr = match (digit1, digit2, digit3, digit4) {
+        (0, 0, 0xE, 0) => Cls,
+        (0, 0, 0xE, 0xE) => Ret,
+        (0, 0, 0xC, _) => ScrollDown { n },
+        (0, 0, 0xD, _) => ScrollUp { n },
+        (0, 0, 0xF, 0xB) => ScrollRight,
+        (0, 0, 0xF, 0xC) => ScrollLeft,
+        (0, 0, 0xF, 0xD) => Exit,
+        (0, 0, 0xF, 0xE) => Lores,
+        (0, 0, 0xF, 0xF) => Hires,
+        (0, _, _, _) => Sys { nnn },
+        (1, _, _, _) => Jump { nnn },
+        (2, _, _, _) => Call { nnn },
+        (3, _, _, _) => SkipEqImm { x, nn },
+        (4, _, _, _) => SkipNeImm { x, nn },
+        (5, _, _, 0) => SkipEqReg { x, y },
+        (5, _, _, 2) => SaveRange { x, y },
+        (5, _, _, 3) => LoadRange { x, y },
+        (6, _, _, _) => LoadImm { x, nn },
+        (7, _, _, _) => AddImm { x, nn },
+        (8, _, _, 0) => Move { x, y },
+        (8, _, _, 1) => Or { x, y },
+        (8, _, _, 2) => And { x, y },
+        (8, _, _, 3) => Xor { x, y },
+        (8, _, _, 4) => Add { x, y },
+        (8, _, _, 5) => Sub { x, y },
+        (8, _, _, 6) => Shr { x, y },
+        (8, _, _, 7) => SubN { x, y },
+        (8, _, _, 0xE) => Shl { x, y },
+        (9, _, _, 0) => SkipNeReg { x, y },
+        (0xA, _, _, _) => LoadI { nnn },
+        (0xB, _, _, _) => JumpOffset { nnn },
+        (0xC, _, _, _) => Random { x, nn },
+        (0xD, _, _, _) => Draw { x, y, n },
+        (0xE, _, 9, 0xE) => SkipKey { x },
+        (0xE, _, 0xA, 1) => SkipNotKey { x },
+        (0xF, 0, 0, 0) => LoadILong,
+        (0xF, _, 0, 1) => Plane { n: x },
+        (0xF, 0, 0, 2) => Audio,
+        (0xF, _, 0, 7) => GetDelay { x },
+        (0xF, _, 0, 0xA) => WaitKey { x },
+        (0xF, _, 1, 5) => SetDelay { x },
+        (0xF, _, 1, 8) => SetSound { x },
+        (0xF, _, 1, 0xE) => AddI { x },
+        (0xF, _, 2, 9) => Font { x },
+        (0xF, _, 3, 0) => BigFont { x },
+        (0xF, _, 3, 3) => Bcd { x },
+        (0xF, _, 3, 0xA) => Pitch { x },
+        (0xF, _, 5, 5) => Store { x },
+        (0xF, _, 6, 5) => Load { x },
+        (0xF, _, 7, 5) => SaveFlags { x },
+        (0xF, _, 8, 5) => LoadFlags { x },
+        (_, _, _, _) => return Err(DecodeError { op }),
+    };
+    Ok(instr)
+}
+
+// Instruction -> opcode. Register and immediate fields are masked to their width
+pub fn encode(instr: Instruction) -> u16 {
+    use Instruction::*;
+
+    let xy = |base: u16, x: u8, y: u8| base | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4);
+    let xnn = |base: u16, x: u8, nn: u8| base | ((x as u16 & 0xF) << 8) | nn as u16;
+    let fx = |x: u8, low: u16| 0xF000 | ((x as u16 & 0xF) << 8) | low;
+
+    match instr {
+        Sys { nnn } => nnn & 0xFFF,
+        Cls => 0x00E0,
+        Ret => 0x00EE,
+        ScrollDown { n } => 0x00C0 | (n as u16 & 0xF),
+        ScrollUp { n } => 0x00D0 | (n as u16 & 0xF),
+        ScrollRight => 0x00FB,
+        ScrollLeft => 0x00FC,
+        Exit => 0x00FD,
+        Lores => 0x00FE,
+        Hires => 0x00FF,
+        Jump { nnn } => 0x1000 | (nnn & 0xFFF),
+        Call { nnn } => 0x2000 | (nnn & 0xFFF),
+        SkipEqImm { x, nn } => xnn(0x3000, x, nn),
+        SkipNeImm { x, nn } => xnn(0x4000, x, nn),
+        SkipEqReg { x, y } => xy(0x5000, x, y),
+        SaveRange { x, y } => xy(0x5002, x, y),
+        LoadRange { x, y } => xy(0x5003, x, y),
+        LoadImm { x, nn } => xnn(0x6000, x, nn),
+        AddImm { x, nn } => xnn(0x7000, x, nn),
+        Move { x, y } => xy(0x8000, x, y),
+        Or { x, y } => xy(0x8001, x, y),
+        And { x, y } => xy(0x8002, x, y),
+        Xor { x, y } => xy(0x8003, x, y),
+        Add { x, y } => xy(0x8004, x, y),
+        Sub { x, y } => xy(0x8005, x, y),
+        Shr { x, y } => xy(0x8006, x, y),
+        SubN { x, y } => xy(0x8007, x, y),
+        Shl { x, y } => xy(0x800E, x, y),
+        SkipNeReg { x, y } => xy(0x9000, x, y),
+        LoadI { nnn } => 0xA000 | (nnn & 0xFFF),
+        JumpOffset { nnn } => 0xB000 | (nnn & 0xFFF),
+        Random { x, nn } => xnn(0xC000, x, nn),
+        Draw { x, y, n } => xy(0xD000, x, y) | (n as u16 & 0xF),
+        SkipKey { x } => xnn(0xE000, x, 0x9E),
+        SkipNotKey { x } => xnn(0xE000, x, 0xA1),
+        LoadILong => 0xF000,
+        Plane { n } => fx(n, 0x01),
+        Audio => 0xF002,
+        GetDelay { x } => fx(x, 0x07),
+        WaitKey { x } => fx(x, 0x0A),
+        SetDelay { x } => fx(x, 0x15),
+        SetSound { x } => fx(x, 0x18),
+        AddI { x } => fx(x, 0x1E),
+        Font { x } => fx(x, 0x29),
+        BigFont { x } => fx(x, 0x30),
+        Bcd { x } => fx(x, 0x33),
+        Pitch { x } => fx(x, 0x3A),
+        Store { x } => fx(x, 0x55),
+        Load { x } => fx(x, 0x65),
+        SaveFlags { x } => fx(x, 0x75),
+        LoadFlags { x } => fx(x, 0x85),
+    }
+}
@@ -1,16 +1,53 @@
 /*
     Emulation : Fetch -> Decode -> Execute
  */
-use rand::random;
+use rand::{random, Rng, RngCore};
+
+pub mod asm;
+pub mod audio;
+pub mod clock;
+mod debug;
+pub mod disasm;
+mod error;
+pub mod gdb;
+pub mod headless;
+pub mod instruction;
+pub mod movie;
+mod quirks;
+pub mod rewind;
+mod rng;
+pub mod romdb;
+pub mod screenshot;
+mod state;
+pub mod trace;
+pub mod video;
+
+pub use debug::{Access, OpcodePattern, Stop, Watchpoint};
+pub use error::{AsmError, DatabaseError, DecodeError, EmuError, MovieError, ScriptError, StateError};
+pub use instruction::{decode, encode, Instruction};
+pub use quirks::Quirks;
+pub use rng::RngAlgorithm;
+
+use debug::Breakpoints;
+use rng::EmuRng;
+use trace::{TraceRecord, Tracer};
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;     // SUPER-CHIP high resolution mode
+pub const HIRES_HEIGHT: usize = 64;
 
 const RAM_SIZE: usize = 4096;   // 4KB RAM SIZE
+const XO_RAM_SIZE: usize = 65536;   // XO-CHIP 64KB RAM SIZE
 const NUM_REGS: usize = 16;     // 16 V Registers
-const STACK_SIZE: usize = 16;   // Stack Size
+pub const STACK_SIZE: usize = 16;   // Stack Size
 const NUM_KEYS: usize = 16;    // 16 Keys
 const FONTSET_SIZE: usize = 80; // 5 Bytes x 16 characters
+const BIG_FONTSET_SIZE: usize = 160;    // 10 Bytes x 16 characters
+const NUM_RPL_FLAGS: usize = 16;    // SUPER-CHIP RPL user flags
+pub const NUM_PLANES: usize = 4;    // XO-CHIP display planes, one bit each in a screen cell
+const PATTERN_SIZE: usize = 16;     // XO-CHIP audio pattern buffer, 128 1-bit samples
+const DEFAULT_PITCH: u8 = 64;       // Pitch that plays the pattern at 4000 samples per second
 
 const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -31,13 +68,75 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80  // F
 ];
 
-const START_ADDR: u16 = 0x200;  // Application Execution Start Address
+// SUPER-CHIP 8x10 digits for FX30, stored right after the small font
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+pub const START_ADDR: u16 = 0x200;  // Application Execution Start Address
+const BIG_FONT_ADDR: u16 = FONTSET_SIZE as u16;    // Big font follows the small one
+pub const FONT_END: u16 = BIG_FONT_ADDR + BIG_FONTSET_SIZE as u16;     // Both fonts live below this address
+
+// Instruction set the core accepts
+// Ordered so that a later platform includes everything before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Mode {
+    #[default]
+    Chip8,
+    SuperChip,      // SUPER-CHIP 1.1: hi-res, scrolling, big font and RPL flags
+    XoChip,         // XO-CHIP: SUPER-CHIP plus 64KB RAM, bit planes and audio patterns
+}
+
+impl Mode {
+    // Quirks the platform is usually run with
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Mode::Chip8 => Quirks::default(),
+            Mode::SuperChip => Quirks::schip(),
+            Mode::XoChip => Quirks::xochip(),
+        }
+    }
+
+    // Bytes of RAM the platform has
+    pub fn ram_size(self) -> usize {
+        match self {
+            Mode::XoChip => XO_RAM_SIZE,
+            _ => RAM_SIZE,
+        }
+    }
+
+    // chip8, schip / superchip or xochip, any case and with or without the dash
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "chip8" | "chip-8" => Some(Mode::Chip8),
+            "schip" | "superchip" | "super-chip" => Some(Mode::SuperChip),
+            "xochip" | "xo-chip" => Some(Mode::XoChip),
+            _ => None,
+        }
+    }
+}
 
 // Emulator Core Structure / Object
 pub struct Emu {
     pc: u16,                                        // 16bit Program Counter
-    ram: [u8; RAM_SIZE],                            // 4KB Memory [Array]
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],   // Screen Data
+    ram: Vec<u8>,                                   // 4KB Memory, 64KB on XO-CHIP
+    screen: [u8; HIRES_WIDTH * HIRES_HEIGHT],       // Screen Data, one bit per plane. Only the first width * height cells are used
+    hires: bool,                                    // SUPER-CHIP 128x64 mode
     v_reg: [u8; NUM_REGS],                          // V Registers
     i_reg: u16,                                     // I Register for Mem Ops
     sp: u16,                                        // Stack pointer
@@ -45,15 +144,32 @@ pub struct Emu {
     keys: [bool; NUM_KEYS],                         // Keys
     dt: u8,                                         // Delay Timer
     st: u8,                                         // Sound Timer
+    quirks: Quirks,                                 // Behaviour of ambiguous opcodes
+    mode: Mode,                                     // Instruction set in use
+    rpl: [u8; NUM_RPL_FLAGS],                       // SUPER-CHIP RPL user flags, kept across resets
+    exited: bool,                                   // Set by 00FD
+    planes: u8,                                     // XO-CHIP planes selected for drawing, bit mask
+    pattern: [u8; PATTERN_SIZE],                    // XO-CHIP audio pattern buffer
+    pitch: u8,                                      // XO-CHIP audio pitch
+    rng: EmuRng,                                    // Random numbers for CXNN, owned so save states can capture it
+    seed: u64,                                      // Seed rng started from, reset() goes back to it
+    debug: Breakpoints,                             // Debugger state, not part of save states
+    tracer: Option<Tracer>,                         // Records every executed instruction when set
 }
 
 impl Emu {
     // Initialization function
     pub fn new() -> Self {
+        Self::with_quirks(Quirks::default())
+    }
+
+    // Initialization with a quirks profile, e.g. Quirks::vip()
+    pub fn with_quirks(quirks: Quirks) -> Self {
         let mut new_emu = Self {
             pc: START_ADDR,
-            ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            ram: vec![0; RAM_SIZE],
+            screen: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            hires: false,
             v_reg: [0; NUM_REGS],
             i_reg: 0,
             sp: 0,
@@ -61,29 +177,135 @@ impl Emu {
             keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
+            quirks,
+            mode: Mode::Chip8,
+            rpl: [0; NUM_RPL_FLAGS],
+            exited: false,
+            planes: 1,
+            pattern: [0; PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            rng: EmuRng::new(RngAlgorithm::default(), 0),
+            seed: 0,
+            debug: Breakpoints::default(),
+            tracer: None,
         };
 
-        new_emu.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);  // Copy sprite data to ram before returning
+        new_emu.set_seed(random());     // Unpredictable unless the frontend asks for a seed
+
+        new_emu.load_fonts();   // Copy sprite data to ram before returning
 
         new_emu                                                        
     }
 
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // Quirks can be swapped at any time, they only affect opcodes executed afterwards
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Switching modes leaves quirks alone, use mode.quirks() to get the matching preset.
+    // RAM is resized to fit the mode, so set it before loading a ROM
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.ram.resize(mode.ram_size(), 0);
+        if mode == Mode::Chip8 {
+            self.set_hires(false);
+        }
+        if mode != Mode::XoChip {
+            self.planes = 1;
+            self.pitch = DEFAULT_PITCH;
+        }
+    }
+
+    // Restart the RNG from seed with the current algorithm, a custom generator is replaced by the default one
+    pub fn set_seed(&mut self, seed: u64) {
+        let algorithm = self.rng.algorithm().unwrap_or_default();
+        self.set_rng_algorithm(algorithm, seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_rng_algorithm(&mut self, algorithm: RngAlgorithm, seed: u64) {
+        self.rng = EmuRng::new(algorithm, seed);
+        self.seed = seed;
+    }
+
+    // None while a custom generator is plugged in
+    pub fn rng_algorithm(&self) -> Option<RngAlgorithm> {
+        self.rng.algorithm()
+    }
+
+    // Plug in any generator. Its state is opaque to us, so save states don't capture it
+    // and loading one leaves the custom generator as it is
+    pub fn set_custom_rng(&mut self, rng: Box<dyn RngCore + Send>) {
+        self.rng = EmuRng::Custom(rng);
+    }
+
+    // True once the ROM executed 00FD, tick does nothing after that
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    fn load_fonts(&mut self) {
+        let big_start = BIG_FONT_ADDR as usize;
+        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.ram[big_start..big_start + BIG_FONTSET_SIZE].copy_from_slice(&BIG_FONTSET);
+    }
+
     // Push function for CPU Stack
-    fn push(&mut self, val: u16) {
+    fn push(&mut self, val: u16) -> Result<(), EmuError> {
+        if self.sp as usize >= STACK_SIZE {
+            return Err(EmuError::StackOverflow);
+        }
         self.stack[self.sp as usize] = val;
         self.sp += 1;
+        Ok(())
     }
 
     // Pop function for CPU Stack
-    fn pop(&mut self) -> u16 {
+    fn pop(&mut self) -> Result<u16, EmuError> {
+        if self.sp == 0 {
+            return Err(EmuError::StackUnderflow);
+        }
         self.sp -= 1;
-        self.stack[self.sp as usize]
+        Ok(self.stack[self.sp as usize])
+    }
+
+    // Fails if any byte of ram[addr..addr + len] is outside RAM
+    fn check_ram(&self, addr: usize, len: usize) -> Result<(), EmuError> {
+        let end = addr + len;
+        if end > self.ram.len() {
+            return Err(EmuError::MemoryOutOfBounds { addr: addr.max(self.ram.len()) });
+        }
+        Ok(())
+    }
+
+    // Every RAM access an instruction makes goes through these two so watchpoints see it.
+    // Bounds are the caller's job, through check_ram
+    fn load_byte(&mut self, addr: usize, access: Access) -> u8 {
+        self.debug.watch(addr, access);
+        self.ram[addr]
+    }
+
+    fn store_byte(&mut self, addr: usize, value: u8) {
+        self.debug.watch(addr, Access::Write);
+        self.ram[addr] = value;
     }
 
     pub fn reset(&mut self) {
         self.pc = START_ADDR;
-        self.ram = [0; RAM_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.ram.fill(0);
+        self.screen = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        self.hires = false;
         self.v_reg = [0; NUM_REGS];
         self.i_reg = 0;
         self.sp = 0;
@@ -91,25 +313,68 @@ impl Emu {
         self.keys = [false; NUM_KEYS];
         self.dt = 0;
         self.st = 0;
-        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.exited = false;
+        self.planes = 1;
+        self.pattern = [0; PATTERN_SIZE];
+        self.pitch = DEFAULT_PITCH;
+        if let Some(algorithm) = self.rng.algorithm() {
+            self.rng = EmuRng::new(algorithm, self.seed);    // Same seed, same run
+        }
+        self.load_fonts();
     }
 
     // Tick runs every CPU cycle
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self) -> Result<(), EmuError> {
+        if self.exited {
+            return Ok(());
+        }
+
+        // State before the instruction, only kept while tracing
+        let before = self.tracer.is_some().then(|| {
+            let next = self.peek_opcode(self.pc.wrapping_add(2)).unwrap_or(0);
+            (self.pc, next, self.v_reg, self.i_reg)
+        });
+
         // Fetch
-        let op = self.fetch();
+        let op = self.fetch()?;
 
         // Decode & Execute
-        self.execute(op);
+        let result = self.execute(op);
+
+        if let (Some(tracer), Some((pc, next, v_before, i_before))) = (self.tracer.as_mut(), before) {
+            tracer.record(TraceRecord {
+                cycle: 0,
+                pc,
+                opcode: op,
+                next,
+                mode: self.mode,
+                v_before,
+                i_before,
+                v_after: self.v_reg,
+                i_after: self.i_reg,
+            });
+        }
+        result
+    }
+
+    // Start tracing, replacing any tracer already set
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    // Stop tracing, finish() the tracer to flush it
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
     }
 
     // Opcode fetch
-    fn fetch(&mut self) -> u16 {
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+    fn fetch(&mut self) -> Result<u16, EmuError> {
+        self.check_ram(self.pc as usize, 2)?;
+        let higher_byte = self.load_byte(self.pc as usize, Access::Execute) as u16;
+        let lower_byte = self.load_byte(self.pc as usize + 1, Access::Execute) as u16;
         let op = (higher_byte << 8) | lower_byte;
-        self.pc += 2;
-        op
+        self.pc = self.pc.wrapping_add(2);
+        Ok(op)
     }
 
     // Modified every frame
@@ -118,123 +383,179 @@ impl Emu {
             self.dt -= 1;
         }
 
+        // The tone plays for as long as the sound timer is non-zero, see is_beeping()
         if self.st > 0 {
-            if self.st == 1 {
-                // BEEP : Not doing as part of the tutorial
-            }
             self.st -= 1;
         }
     }
 
+    // True while the sound timer runs, frontends poll this once per frame to drive their audio
+    pub fn is_beeping(&self) -> bool {
+        self.st > 0
+    }
+
     // Decode and execute function
-    fn execute(&mut self, op: u16){
-        // Separate each digit of opcode
-        let digit1 = (op & 0xF000) >> 12;
-        let digit2 = (op & 0x0F00) >> 8;
-        let digit3 = (op & 0x00F0) >> 4;
-        let digit4 = op & 0x000F;
-
-        match (digit1, digit2, digit3, digit4) {
+    fn execute(&mut self, op: u16) -> Result<(), EmuError> {
+        let pc = self.pc.wrapping_sub(2);
+        let instr = decode(op).map_err(|_| EmuError::UnknownOpcode { pc, op })?;
+
+        // Instructions from a newer platform than the one we run are as unknown as garbage
+        if instr.mode() > self.mode {
+            return Err(EmuError::UnknownOpcode { pc, op });
+        }
+
+        match instr {
             //NOP
-            (0, 0, 0, 0) => return,
+            Instruction::Sys { nnn: 0 } => (),
+
+            // SYS NNN : Machine code routines can't run here
+            Instruction::Sys { .. } => {
+                return Err(EmuError::UnknownOpcode { pc, op });
+            },
 
             //CLS
-            (0, 0, 0xE, 0) => {
-                self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT]
+            Instruction::Cls => {
+                let planes = self.planes;
+                self.screen.iter_mut().for_each(|px| *px &= !planes);     // Only the selected planes get cleared
+            },
+
+            // SCROLL UP N
+            Instruction::ScrollUp { n } => {
+                self.scroll_up(n as usize);
+            },
+
+            // SCROLL DOWN N
+            Instruction::ScrollDown { n } => {
+                self.scroll_down(n as usize);
+            },
+
+            // SCROLL RIGHT 4
+            Instruction::ScrollRight => {
+                self.scroll_horizontal(4);
+            },
+
+            // SCROLL LEFT 4
+            Instruction::ScrollLeft => {
+                self.scroll_horizontal(-4);
+            },
+
+            // EXIT
+            Instruction::Exit => {
+                self.exited = true;
+            },
+
+            // LORES
+            Instruction::Lores => {
+                self.set_hires(false);
+            },
+
+            // HIRES
+            Instruction::Hires => {
+                self.set_hires(true);
             },
 
             // RET
-            (0, 0, 0xE, 0xE) => {
-                let ret_addr = self.pop();  // Pop from CPU stack for function call
+            Instruction::Ret => {
+                let ret_addr = self.pop()?; // Pop from CPU stack for function call
                 self.pc = ret_addr;
             },
 
             // JMP NNN
-            (1, _, _, _) => {
-                let nnn = op & 0xFFF;       // Get the addr from the opcode
+            Instruction::Jump { nnn } => {
                 self.pc = nnn;                   // Set PC to addr
-            },  
+            },
 
             // CALL NNN
-            (2, _, _, _) => {
-                let nnn = op & 0xFFF;       
-                self.push(self.pc);         // Push PC to Stack
+            Instruction::Call { nnn } => {
+                self.push(self.pc)?;        // Push PC to Stack
                 self.pc = nnn;                   // Set PC to addr
             },
 
             // SKIP VX == NN
-            (3, _, _, _) => {
-                let x = digit2 as usize; // Indexing array in rust should be in usize
-                let nn = (op & 0xFF) as u8;
-                if self.v_reg[x] == nn {
-                    self.pc += 2;               // Skip the next opcode i.e., skip 2 bytes
+            Instruction::SkipEqImm { x, nn } => {
+                if self.v_reg[x as usize] == nn {
+                    self.skip();                // Skip the next opcode
                 }
             },
 
             // SKIP VX != NN
-            (4, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                if self.v_reg[x] != nn {
-                    self.pc += 2;               // Skip the next opcode i.e., skip 2 bytes
+            Instruction::SkipNeImm { x, nn } => {
+                if self.v_reg[x as usize] != nn {
+                    self.skip();                // Skip the next opcode
                 }
             },
 
             // SKIP VX == VY
-            (5, _, _, 0) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                if self.v_reg[x] == self.v_reg[y] {
-                    self.pc += 2;
+            Instruction::SkipEqReg { x, y } => {
+                if self.v_reg[x as usize] == self.v_reg[y as usize] {
+                    self.skip();
+                }
+            },
+
+            // SAVE VX - VY
+            Instruction::SaveRange { x, y } => {
+                let i = self.i_reg as usize;
+                let regs = reg_range(x as usize, y as usize);
+                self.check_ram(i, regs.len())?;
+                for (offset, reg) in regs.into_iter().enumerate() {
+                    self.store_byte(i + offset, self.v_reg[reg]);
+                }
+            },
+
+            // LOAD VX - VY
+            Instruction::LoadRange { x, y } => {
+                let i = self.i_reg as usize;
+                let regs = reg_range(x as usize, y as usize);
+                self.check_ram(i, regs.len())?;
+                for (offset, reg) in regs.into_iter().enumerate() {
+                    self.v_reg[reg] = self.load_byte(i + offset, Access::Read);
                 }
             },
 
             // VX = NN
-            (6, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                self.v_reg[x] = nn;
+            Instruction::LoadImm { x, nn } => {
+                self.v_reg[x as usize] = nn;
             },
 
             // VX += NN
-            (7, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
+            Instruction::AddImm { x, nn } => {
+                let x = x as usize;
                 self.v_reg[x] = self.v_reg[x].wrapping_add(nn); // We don't want carry. And overflow will cause panic.
             },
 
             // VX = VY
-            (8, _, _, 0) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_reg[x] = self.v_reg[y];
+            Instruction::Move { x, y } => {
+                self.v_reg[x as usize] = self.v_reg[y as usize];
             },
 
             // VX |= VY
-            (8, _, _, 1) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_reg[x] |= self.v_reg[y];
+            Instruction::Or { x, y } => {
+                self.v_reg[x as usize] |= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             },
 
             // VX &= VY
-            (8, _, _, 2) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_reg[x] &= self.v_reg[y];
+            Instruction::And { x, y } => {
+                self.v_reg[x as usize] &= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             },
 
             // VX ^= VY
-            (8, _, _, 3) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_reg[x] ^= self.v_reg[y];
+            Instruction::Xor { x, y } => {
+                self.v_reg[x as usize] ^= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             },
 
             // VX += VY
-            (8, _, _, 4) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::Add { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (new_vx, carry) = self.v_reg[x].overflowing_add(self.v_reg[y]); // Returns wrapping sum if carry is generated
                 let new_vf = if carry { 1 } else { 0 };                                   // Value to update carry flag
@@ -244,9 +565,9 @@ impl Emu {
             },
 
             // VX -= VY
-            (8, _, _, 5) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::Sub { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (new_vx, borrow) = self.v_reg[x].overflowing_sub(self.v_reg[y]); // Returns wrapping sum if carry is generated
                 let new_vf = if borrow { 0 } else { 1 };                                   // Value to update carry flag
@@ -256,8 +577,13 @@ impl Emu {
             }
 
             // VX >>= 1
-            (8, _, _, 6) => {
-                let x = digit2 as usize;
+            Instruction::Shr { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
+
+                if !self.quirks.shift {
+                    self.v_reg[x] = self.v_reg[y];          // VIP shifts VY and stores it in VX
+                }
 
                 let lsb = self.v_reg[x] & 1;                // Store the LSB before Right Shift
                 self.v_reg[x] >>= 1;                            
@@ -265,9 +591,9 @@ impl Emu {
             },
 
             // VX = VY - VX
-            (8, _, _, 7) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::SubN { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (new_vx, borrow) = self.v_reg[y].overflowing_sub(self.v_reg[x]); // Returns wrapping sub if carry is generated
                 let new_vf = if borrow { 0 } else { 1 }; 
@@ -277,8 +603,13 @@ impl Emu {
             },
 
             // VX <<= 1
-            (8, _, _, 0xE) => {
-                let x = digit2 as usize;
+            Instruction::Shl { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
+
+                if !self.quirks.shift {
+                    self.v_reg[x] = self.v_reg[y];
+                }
 
                 let msb = (self.v_reg[x] >> 7) & 1;         // Store the MSB before Left Shift
                 self.v_reg[x] <<= 1;                            
@@ -286,70 +617,34 @@ impl Emu {
             },
 
             // SKIP VX != VY
-            (9, _, _, 0) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-
-                if self.v_reg[x] != self.v_reg[y] {
-                    self.pc += 2;
+            Instruction::SkipNeReg { x, y } => {
+                if self.v_reg[x as usize] != self.v_reg[y as usize] {
+                    self.skip();
                 }
             },
 
             // I - NNN
-            (0xA, _, _, _) => {
-                let nnn = op & 0xFFF;
+            Instruction::LoadI { nnn } => {
                 self.i_reg = nnn;
             },
 
             // JMP V0 + NNN
-            (0xB, _, _, _) => {
-                let nnn = op & 0xFFF;
-                self.pc = (self.v_reg[0] as u16) + nnn;
+            Instruction::JumpOffset { nnn } => {
+                let reg = if self.quirks.jump { (nnn >> 8) as usize } else { 0 };  // BXNN jumps to XNN + VX
+                self.pc = (self.v_reg[reg] as u16) + nnn;
             },
 
             // VX = rand() & NN
-            (0xC, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                let rng: u8 = random();
-                self.v_reg[x] = rng & nn;
-            },
-
-            // Draw Sprite at (VX, VY) of Height N
-            (0xD, _, _, _) => {
-                // Get (x,y) coordinate for our sprite
-                let x_coord = self.v_reg[digit2 as usize] as u16;
-                let y_coord = self.v_reg[digit3 as usize] as u16;
-
-                // The last digit determines height of the sprite
-                let num_rows = digit4;
-
-                // Keep track of flipped pixels
-                let mut flipped = false;
-
-                // Iterate over each row of our sprite
-                for y_line in 0..num_rows {
-                    // Determine memory address containing this row data
-                    let addr = self.i_reg + y_line as u16;
-                    let pixels = self.ram[addr as usize];
-
-                    // Iterate over each column in our row
-                    for x_line in 0..8 {
-                        // Use a mask to fetch current pixel's bit. Only flip if a 1
-                        if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                            // Sprites should wrap around screen, so apply modulo
-                            let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                            let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
-
-                            // Get pixels's index for our 1D screen array
-                            let idx = x + SCREEN_WIDTH * y;
-
-                            // Check if we are about to flip the pixel and set
-                            flipped |= self.screen[idx];
-                            self.screen[idx] ^= true;
-                        }
-                    }
-                }
+            Instruction::Random { x, nn } => {
+                let rng: u8 = self.rng.gen();
+                self.v_reg[x as usize] = rng & nn;
+            },
+
+            // Draw Sprite at (VX, VY) of Height N, DXY0 draws a 16x16 sprite on SUPER-CHIP
+            Instruction::Draw { x, y, n } => {
+                let x_coord = self.v_reg[x as usize] as usize;
+                let y_coord = self.v_reg[y as usize] as usize;
+                let flipped = self.draw_sprite(x_coord, y_coord, n as usize)?;
 
                 // Populate VF register
                 if flipped {
@@ -361,34 +656,50 @@ impl Emu {
             },
 
             // SKIP KEY PRESS
-            (0xE, _, 0x9, 0xE) => {
-                let x = digit2 as usize;
-                let vx = self.v_reg[x];
-                let key = self.keys[vx as usize];
+            Instruction::SkipKey { x } => {
+                let vx = self.v_reg[x as usize];
+                let key = self.key(vx)?;
                 if key {
-                    self.pc += 2;
+                    self.skip();
                 }
             },
 
             // SKIP KEY RELEASE
-            (0xE, _, 0xA, 1) => {
-                let x = digit2 as usize;
-                let vx = self.v_reg[x];
-                let key = self.keys[vx as usize];
+            Instruction::SkipNotKey { x } => {
+                let vx = self.v_reg[x as usize];
+                let key = self.key(vx)?;
                 if !key {
-                    self.pc += 2;
+                    self.skip();
+                }
+            },
+
+            // I = NNNN, the address is the next 16 bit word
+            Instruction::LoadILong => {
+                self.i_reg = self.fetch()?;
+            },
+
+            // SELECT PLANES N
+            Instruction::Plane { n } => {
+                self.planes = n;
+            },
+
+            // AUDIO PATTERN = RAM[I..I + 16]
+            Instruction::Audio => {
+                let i = self.i_reg as usize;
+                self.check_ram(i, PATTERN_SIZE)?;
+                for offset in 0..PATTERN_SIZE {
+                    self.pattern[offset] = self.load_byte(i + offset, Access::Read);
                 }
             },
 
             // VX = DT
-            (0xF, _, 0, 7) => {
-                let x = digit2 as usize;
-                self.v_reg[x] = self.dt;
+            Instruction::GetDelay { x } => {
+                self.v_reg[x as usize] = self.dt;
             },
 
             // WAIT KEY
-            (0xF, _, 0, 0xA) => {
-                let x = digit2 as usize;
+            Instruction::WaitKey { x } => {
+                let x = x as usize;
                 let mut pressed = false;
                 for i in 0..self.keys.len() {
                     if self.keys[i] {
@@ -400,40 +711,43 @@ impl Emu {
 
                 if !pressed {
                     // Redo opcode
-                    self.pc -= 2;                       // Resetting to last instruction because we want to capture the user input. Simply running a loop would cause it to never end
+                    self.pc = self.pc.wrapping_sub(2);  // Resetting to last instruction because we want to capture the user input. Simply running a loop would cause it to never end
                 }
             },
 
             // DT = VX
-            (0xF, _, 1, 5) => {
-                let x = digit2 as usize;
-                self.dt = self.v_reg[x];
+            Instruction::SetDelay { x } => {
+                self.dt = self.v_reg[x as usize];
             },
 
             // ST = VX
-            (0xF, _, 1, 8) => {
-                let x = digit2 as usize;
-                self.st = self.v_reg[x];
+            Instruction::SetSound { x } => {
+                self.st = self.v_reg[x as usize];
             },
 
             // I += VX
-            (0xF, _, 1, 0xE) => {
-                let x = digit2 as usize;
-                let vx = self.v_reg[x] as u16;
+            Instruction::AddI { x } => {
+                let vx = self.v_reg[x as usize] as u16;
                 self.i_reg = self.i_reg.wrapping_add(vx);
             },
 
             // I = FONT
-            (0xF, _, 2, 9) => {
-                let x = digit2 as usize;
-                let c = self.v_reg[x] as u16;
+            Instruction::Font { x } => {
+                let c = self.v_reg[x as usize] as u16;
                 self.i_reg = c * 5;                     // Our numbers are stored from 0 address and each number sprite is 5 bytes
             },
 
+            // I = BIG FONT
+            Instruction::BigFont { x } => {
+                let c = (self.v_reg[x as usize] & 0xF) as u16;
+                self.i_reg = BIG_FONT_ADDR + c * 10;   // Each big digit is 10 bytes
+            },
+
             // BCD
-            (0xF, _, 3, 3) => {                         // This is not the best BCD algorithm. Just for understanding.
-                let x = digit2 as usize;
-                let vx = self.v_reg[x] as f32;
+            Instruction::Bcd { x } => {                 // This is not the best BCD algorithm. Just for understanding.
+                let vx = self.v_reg[x as usize] as f32;
+                let i = self.i_reg as usize;
+                self.check_ram(i, 3)?;
 
                 // Fetch the hundreds digit
                 let hundreds = (vx / 100.0).floor() as u8;
@@ -444,47 +758,250 @@ impl Emu {
                 // Fetch the ones digit
                 let ones = (vx % 10.0) as u8;
 
-                self.ram[self.i_reg as usize] = hundreds;
-                self.ram[(self.i_reg + 1) as usize] = tens;
-                self.ram[(self.i_reg + 2) as usize] = ones;
+                self.store_byte(i, hundreds);
+                self.store_byte(i + 1, tens);
+                self.store_byte(i + 2, ones);
+            },
+
+            // PITCH = VX
+            Instruction::Pitch { x } => {
+                self.pitch = self.v_reg[x as usize];
             },
 
             // STORE V0 - VX
-            (0xF, _, 5, 5) => {
-                let x = digit2 as usize;
+            Instruction::Store { x } => {
+                let x = x as usize;
                 let i = self.i_reg as usize;
+                self.check_ram(i, x + 1)?;
                 for idx in 0..=x {
-                    self.ram[i + idx] = self.v_reg[idx];
+                    self.store_byte(i + idx, self.v_reg[idx]);
+                }
+                if self.quirks.memory {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
             },
 
             // LOAD V0 - VX
-            (0xF, _, 6, 5) => {
-                let x = digit2 as usize;
+            Instruction::Load { x } => {
+                let x = x as usize;
                 let i = self.i_reg as usize;
+                self.check_ram(i, x + 1)?;
                 for idx in 0..=x {
-                    self.v_reg[idx] = self.ram[i + idx];
+                    self.v_reg[idx] = self.load_byte(i + idx, Access::Read);
                 }
+                if self.quirks.memory {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
+                }
+            },
+
+            // SAVE V0 - VX TO RPL FLAGS
+            Instruction::SaveFlags { x } => {
+                let x = x as usize;
+                self.rpl[..=x].copy_from_slice(&self.v_reg[..=x]);
             },
 
-            //Unimplemented Case : Mandatory for RUST
-            (_, _, _, _) => unimplemented!("Unimplemented opcode: {}", op),
+            // LOAD V0 - VX FROM RPL FLAGS
+            Instruction::LoadFlags { x } => {
+                let x = x as usize;
+                self.v_reg[..=x].copy_from_slice(&self.rpl[..=x]);
+            },
         }
+
+        Ok(())
     }
 
-    // Returns display array to frontend
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    fn is_schip(&self) -> bool {
+        self.mode != Mode::Chip8
     }
 
-    // Handles keypress
+    fn is_xochip(&self) -> bool {
+        self.mode == Mode::XoChip
+    }
+
+    // Skip the next instruction, F000 NNNN is 4 bytes long on XO-CHIP.
+    // Peeking at the next opcode isn't a program access, so watchpoints don't see it
+    fn skip(&mut self) {
+        let pc = self.pc as usize;
+        let long = self.is_xochip()
+            && pc + 1 < self.ram.len()
+            && self.ram[pc] == 0xF0
+            && self.ram[pc + 1] == 0x00;
+        let len = if long { 4 } else { 2 };
+        self.pc = self.pc.wrapping_add(len);
+    }
+
+    // XOR a sprite from ram[I] onto the selected planes, returns true if any pixel got turned off.
+    // With several planes selected the sprite data for each plane follows the previous one
+    fn draw_sprite(&mut self, x_coord: usize, y_coord: usize, height: usize) -> Result<bool, EmuError> {
+        let width = self.display_width();
+        let screen_height = self.display_height();
+
+        // DXY0 is a 16x16 sprite with two bytes per row on SUPER-CHIP
+        let (num_rows, num_cols) = if height == 0 && self.is_schip() { (16, 16) } else { (height, 8) };
+        let bytes_per_row = num_cols / 8;
+        let sprite_len = num_rows * bytes_per_row;
+
+        // Whole sprite has to be in RAM before we touch the screen
+        let i = self.i_reg as usize;
+        self.check_ram(i, sprite_len * self.planes.count_ones() as usize)?;
+
+        // The starting point always wraps
+        let x_coord = x_coord % width;
+        let y_coord = y_coord % screen_height;
+
+        // Keep track of flipped pixels
+        let mut flipped = false;
+
+        let mut addr = i;
+        for plane in 0..NUM_PLANES {
+            let bit = 1 << plane;
+            if self.planes & bit == 0 {
+                continue;
+            }
+
+            // Iterate over each row of our sprite
+            for y_line in 0..num_rows {
+                // Iterate over each column in our row
+                for x_line in 0..num_cols {
+                    // Determine memory address containing this pixel and use a mask to fetch its bit. Only flip if a 1
+                    let pixels = self.load_byte(addr + y_line * bytes_per_row + x_line / 8, Access::Read);
+                    if (pixels & (0b1000_0000 >> (x_line % 8))) != 0 {
+                        let mut x = x_coord + x_line;
+                        let mut y = y_coord + y_line;
+
+                        // Either clip the parts that fall off screen or wrap them around
+                        if self.quirks.clip && (x >= width || y >= screen_height) {
+                            continue;
+                        }
+                        x %= width;
+                        y %= screen_height;
+
+                        // Get pixels's index for our 1D screen array
+                        let idx = x + width * y;
+
+                        // Check if we are about to flip the pixel and set
+                        flipped |= self.screen[idx] & bit != 0;
+                        self.screen[idx] ^= bit;
+                    }
+                }
+            }
+
+            addr += sprite_len;
+        }
+
+        Ok(flipped)
+    }
+
+    // Switching resolution clears the screen
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.screen = [0; HIRES_WIDTH * HIRES_HEIGHT];
+    }
+
+    // Move the selected planes down by n pixels, blank rows come in at the top
+    fn scroll_down(&mut self, n: usize) {
+        let height = self.display_height();
+        for y in (0..height).rev() {
+            self.copy_row(y.checked_sub(n), y);
+        }
+    }
+
+    // Move the selected planes up by n pixels, blank rows come in at the bottom
+    fn scroll_up(&mut self, n: usize) {
+        let height = self.display_height();
+        for y in 0..height {
+            self.copy_row(Some(y + n).filter(|&src| src < height), y);
+        }
+    }
+
+    // Move the selected planes by dx pixels, positive is to the right
+    fn scroll_horizontal(&mut self, dx: isize) {
+        let width = self.display_width();
+        let height = self.display_height();
+        let planes = self.planes;
+        for y in 0..height {
+            let row = &mut self.screen[width * y..width * (y + 1)];
+            let old = row.to_vec();
+            for (x, px) in row.iter_mut().enumerate() {
+                let src = x as isize - dx;
+                let moved = if src >= 0 && (src as usize) < width { old[src as usize] } else { 0 };
+                *px = (*px & !planes) | (moved & planes);
+            }
+        }
+    }
+
+    // Copy the selected planes of row src over row dst, None blanks dst
+    fn copy_row(&mut self, src: Option<usize>, dst: usize) {
+        let width = self.display_width();
+        let planes = self.planes;
+        for x in 0..width {
+            let moved = src.map_or(0, |src| self.screen[x + width * src]);
+            let px = &mut self.screen[x + width * dst];
+            *px = (*px & !planes) | (moved & planes);
+        }
+    }
+
+    // Key state for a key skip, VX has to name one of the 16 keys
+    fn key(&self, vx: u8) -> Result<bool, EmuError> {
+        self.keys
+            .get(vx as usize)
+            .copied()
+            .ok_or(EmuError::InvalidKey { vx })
+    }
+
+    // Returns display array to frontend, display_width() pixels per row.
+    // Each pixel is a plane mask: 0 is off, bit n is set when plane n is lit
+    pub fn get_display(&self) -> &[u8] {
+        &self.screen[..self.display_width() * self.display_height()]
+    }
+
+    pub fn display_width(&self) -> usize {
+        if self.hires { HIRES_WIDTH } else { SCREEN_WIDTH }
+    }
+
+    pub fn display_height(&self) -> usize {
+        if self.hires { HIRES_HEIGHT } else { SCREEN_HEIGHT }
+    }
+
+    // XO-CHIP audio pattern, 128 1-bit samples played MSB first while the sound timer runs
+    pub fn audio_pattern(&self) -> &[u8] {
+        &self.pattern
+    }
+
+    // Playback rate of the audio pattern in samples per second, set by FX3A
+    pub fn audio_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    // Handles keypress, anything past key F is ignored
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
-        self.keys[idx] = pressed;
+        if let Some(key) = self.keys.get_mut(idx) {
+            *key = pressed;
+        }
     }
 
-    pub fn load(&mut self, data: &[u8]) {
+    pub fn load(&mut self, data: &[u8]) -> Result<(), EmuError> {
         let start = START_ADDR as usize;
         let end = (START_ADDR as usize) + data.len();
+        if end > self.ram.len() {
+            return Err(EmuError::RomTooLarge { size: data.len(), max: self.ram.len() - start });
+        }
         self.ram[start..end].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+// Registers X through Y for 5XY2/5XY3, walked backwards when X > Y
+fn reg_range(x: usize, y: usize) -> Vec<usize> {
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
+
+impl Default for Emu {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,241 @@
+/*
+    Movies : key presses recorded by frame, replayed to reproduce a session exactly
+
+    A movie holds everything a run depends on besides the ROM itself: the SHA-1 of the ROM,
+    mode, quirks, RNG algorithm and seed, instructions per frame and the key events. Replaying
+    it with the same ROM gives the same machine frame for frame. When recording finishes the
+    SHA-1 of the final screen is stored too, so a replay can check it ended up in the same place.
+
+    Files are text, a header then key events in the key script format (see headless):
+
+        chip8-movie 1
+        rom 2d8a7f1b...
+        mode chip8
+        quirks vf_reset memory clip
+        rng chacha8 1234
+        ticks 10
+        frames 900
+        screen 91c0e6f2...
+        30 5 down
+        33 5 up
+ */
+use std::fmt;
+
+use crate::headless::{parse_event, KeyEvent, Runner};
+use crate::{Emu, Mode, MovieError, Quirks, RngAlgorithm, NUM_KEYS};
+
+const MAGIC: &str = "chip8-movie";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_sha1: String,           // Lowercase hex
+    pub mode: Mode,
+    pub quirks: Quirks,
+    pub rng: RngAlgorithm,
+    pub seed: u64,
+    pub ticks_per_frame: usize,
+    pub frames: u64,                // Length of the movie
+    pub events: Vec<KeyEvent>,      // Sorted by frame
+    pub screen_sha1: Option<String>,    // Final screen, see screen_sha1()
+}
+
+impl Movie {
+    // Start recording a session of rom on emu, which should be set up but not have run yet
+    pub fn new(rom: &[u8], emu: &Emu, ticks_per_frame: usize) -> Self {
+        Self {
+            rom_sha1: sha1_hex(rom),
+            mode: emu.mode(),
+            quirks: emu.quirks(),
+            rng: emu.rng_algorithm().unwrap_or_default(),   // A custom generator can't be replayed, the default stands in
+            seed: emu.seed(),
+            ticks_per_frame,
+            frames: 0,
+            events: Vec::new(),
+            screen_sha1: None,
+        }
+    }
+
+    // Record a key change before frame runs. Repeats of the key's current state are dropped,
+    // so frontends can pass on every key event they get
+    pub fn record_key(&mut self, frame: u64, key: u8, pressed: bool) {
+        let held = self.events.iter().rev().find(|e| e.key == key).is_some_and(|e| e.pressed);
+        if held != pressed && (key as usize) < NUM_KEYS {
+            self.events.push(KeyEvent { frame, key, pressed });
+        }
+    }
+
+    // Stop recording after frames frames, remembering the screen emu ended on
+    pub fn end(&mut self, frames: u64, emu: &Emu) {
+        self.frames = frames;
+        self.screen_sha1 = Some(screen_sha1(emu));
+    }
+
+    // Fresh emulator set up the way the recording was, with rom loaded
+    pub fn setup(&self, rom: &[u8]) -> Result<Emu, MovieError> {
+        let found = sha1_hex(rom);
+        if found != self.rom_sha1 {
+            return Err(MovieError::RomMismatch { expected: self.rom_sha1.clone(), found });
+        }
+        let mut emu = Emu::with_quirks(self.quirks);
+        emu.set_mode(self.mode);
+        emu.set_rng_algorithm(self.rng, self.seed);
+        emu.load(rom).map_err(MovieError::Load)?;
+        Ok(emu)
+    }
+
+    // Events that apply before frame runs
+    pub fn events_at(&self, frame: u64) -> impl Iterator<Item = &KeyEvent> {
+        self.events.iter().filter(move |e| e.frame == frame)
+    }
+
+    // Headless runner that plays the movie through to its last frame
+    pub fn runner(&self) -> Runner {
+        Runner {
+            frames: self.frames,
+            ticks_per_frame: self.ticks_per_frame,
+            script: self.events.clone(),
+            ..Runner::default()
+        }
+    }
+
+    // Whether emu shows the screen the recording ended on, None if the movie doesn't say
+    pub fn screen_matches(&self, emu: &Emu) -> Option<bool> {
+        self.screen_sha1.as_ref().map(|expected| *expected == screen_sha1(emu))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MovieError> {
+        let mut lines = text.lines().enumerate()
+            .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let error = |line: usize, message: String| MovieError::Parse { line, message };
+
+        match lines.next() {
+            Some((_, line)) if line == format!("{} {}", MAGIC, VERSION) => (),
+            Some((number, line)) if line.starts_with(MAGIC) => return Err(error(number, format!("unsupported movie version: {}", line))),
+            other => return Err(error(other.map_or(1, |(n, _)| n), "not a movie file".to_string())),
+        }
+
+        let mut movie = Movie {
+            rom_sha1: String::new(),
+            mode: Mode::Chip8,
+            quirks: Quirks::default(),
+            rng: RngAlgorithm::default(),
+            seed: 0,
+            ticks_per_frame: crate::headless::TICKS_PER_FRAME,
+            frames: 0,
+            events: Vec::new(),
+            screen_sha1: None,
+        };
+        let mut quirks_given = false;
+        for (number, line) in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let bad = |what: &str| error(number, format!("bad {}: {}", what, line));
+            match words.as_slice() {
+                ["rom", hash] if is_sha1(hash) => movie.rom_sha1 = hash.to_ascii_lowercase(),
+                ["screen", hash] if is_sha1(hash) => movie.screen_sha1 = Some(hash.to_ascii_lowercase()),
+                ["rom" | "screen", _] => return Err(bad("SHA-1")),
+                ["mode", name] => movie.mode = Mode::from_name(name).ok_or_else(|| bad("mode"))?,
+                ["quirks", names @ ..] => {
+                    movie.quirks = parse_quirks(names).ok_or_else(|| bad("quirks"))?;
+                    quirks_given = true;
+                },
+                ["rng", name, seed] => {
+                    movie.rng = RngAlgorithm::from_name(name).ok_or_else(|| bad("RNG"))?;
+                    movie.seed = seed.parse().map_err(|_| bad("seed"))?;
+                },
+                ["ticks", n] => movie.ticks_per_frame = n.parse().map_err(|_| bad("ticks per frame"))?,
+                ["frames", n] => movie.frames = n.parse().map_err(|_| bad("frame count"))?,
+                _ => movie.events.push(parse_event(line).map_err(|message| error(number, message))?),
+            }
+        }
+        if movie.rom_sha1.is_empty() {
+            return Err(error(1, "no rom line".to_string()));
+        }
+        if !quirks_given {
+            movie.quirks = movie.mode.quirks();
+        }
+        movie.events.sort_by_key(|e| e.frame);
+        Ok(movie)
+    }
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", MAGIC, VERSION)?;
+        writeln!(f, "rom {}", self.rom_sha1)?;
+        writeln!(f, "mode {}", mode_name(self.mode))?;
+        writeln!(f, "quirks {}", quirk_names(self.quirks).join(" "))?;
+        writeln!(f, "rng {} {}", rng_name(self.rng), self.seed)?;
+        writeln!(f, "ticks {}", self.ticks_per_frame)?;
+        writeln!(f, "frames {}", self.frames)?;
+        if let Some(hash) = &self.screen_sha1 {
+            writeln!(f, "screen {}", hash)?;
+        }
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn sha1_hex(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
+
+// SHA-1 of the screen's size and plane masks
+pub fn screen_sha1(emu: &Emu) -> String {
+    let mut hash = sha1_smol::Sha1::new();
+    hash.update(&(emu.display_width() as u16).to_le_bytes());
+    hash.update(&(emu.display_height() as u16).to_le_bytes());
+    hash.update(emu.get_display());
+    hash.digest().to_string()
+}
+
+fn is_sha1(text: &str) -> bool {
+    text.len() == 40 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Chip8 => "chip8",
+        Mode::SuperChip => "schip",
+        Mode::XoChip => "xochip",
+    }
+}
+
+fn rng_name(rng: RngAlgorithm) -> &'static str {
+    match rng {
+        RngAlgorithm::ChaCha8 => "chacha8",
+        RngAlgorithm::Xorshift => "xorshift",
+    }
+}
+
+// Quirks are listed by the names of the ones turned on
+fn quirk_names(quirks: Quirks) -> Vec<&'static str> {
+    [
+        (quirks.vf_reset, "vf_reset"),
+        (quirks.memory, "memory"),
+        (quirks.shift, "shift"),
+        (quirks.jump, "jump"),
+        (quirks.clip, "clip"),
+    ]
+    .into_iter()
+    .filter_map(|(on, name)| on.then_some(name))
+    .collect()
+}
+
+fn parse_quirks(names: &[&str]) -> Option<Quirks> {
+    let mut quirks = Quirks { vf_reset: false, memory: false, shift: false, jump: false, clip: false };
+    for name in names {
+        match *name {
+            "vf_reset" => quirks.vf_reset = true,
+            "memory" => quirks.memory = true,
+            "shift" => quirks.shift = true,
+            "jump" => quirks.jump = true,
+            "clip" => quirks.clip = true,
+            _ => return None,
+        }
+    }
+    Some(quirks)
+}
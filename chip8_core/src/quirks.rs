@@ -0,0 +1,80 @@
+// Behaviour toggles for opcodes that CHIP-8 interpreters never agreed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    pub vf_reset: bool,     // 8XY1/8XY2/8XY3 reset VF to 0
+    pub memory: bool,       // FX55/FX65 leave I pointing past the last register touched
+    pub shift: bool,        // 8XY6/8XYE shift VX in place and ignore VY
+    pub jump: bool,         // BNNN jumps to XNN + VX instead of NNN + V0
+    pub clip: bool,         // DXYN clips sprites at the screen edge instead of wrapping them
+}
+
+impl Quirks {
+    // Original COSMAC VIP interpreter
+    pub const fn vip() -> Self {
+        Self {
+            vf_reset: true,
+            memory: true,
+            shift: false,
+            jump: false,
+            clip: true,
+        }
+    }
+
+    // CHIP-48 on the HP-48. It really adds X to I on FX55/FX65, which we treat as leaving I alone
+    pub const fn chip48() -> Self {
+        Self {
+            vf_reset: false,
+            memory: false,
+            shift: true,
+            jump: true,
+            clip: true,
+        }
+    }
+
+    // SUPER-CHIP 1.1
+    pub const fn schip() -> Self {
+        Self {
+            vf_reset: false,
+            memory: false,
+            shift: true,
+            jump: true,
+            clip: true,
+        }
+    }
+
+    // XO-CHIP as implemented by Octo
+    pub const fn xochip() -> Self {
+        Self {
+            vf_reset: false,
+            memory: true,
+            shift: false,
+            jump: false,
+            clip: false,
+        }
+    }
+
+    // Preset for a platform name, any case, with or without the dash. chip8 means the original
+    // COSMAC VIP behaviour, not the default below
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vip" | "chip8" | "chip-8" => Some(Self::vip()),
+            "chip48" | "chip-48" => Some(Self::chip48()),
+            "schip" | "superchip" | "super-chip" => Some(Self::schip()),
+            "xochip" | "xo-chip" => Some(Self::xochip()),
+            _ => None,
+        }
+    }
+}
+
+// What the core did before quirks were configurable
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            vf_reset: false,
+            memory: false,
+            shift: true,
+            jump: false,
+            clip: false,
+        }
+    }
+}
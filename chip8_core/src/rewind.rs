@@ -0,0 +1,160 @@
+/*
+    Rewind : ring buffer of per-frame save states
+
+    Only the newest state is kept whole. Every older frame is stored as the XOR of itself and
+    the frame after it, run-length encoded, so the parts of RAM and the screen that didn't change
+    cost a couple of bytes. Stepping back undoes one delta at a time. A delta that can't be
+    undone drops the whole history rather than handing back a bad state.
+ */
+use std::collections::VecDeque;
+
+pub struct Rewind {
+    frames: VecDeque<Vec<u8>>,  // Encoded deltas, oldest at the front
+    latest: Vec<u8>,            // Full state of the newest frame
+    used: usize,                // Bytes held by frames
+    budget: usize,              // Oldest frames get dropped once used goes past this
+}
+
+impl Rewind {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            latest: Vec::new(),
+            used: 0,
+            budget,
+        }
+    }
+
+    // Record the state of the frame that just ran
+    pub fn push(&mut self, state: Vec<u8>) {
+        if !self.latest.is_empty() {
+            let delta = encode(&state, &self.latest);
+            self.used += delta.len();
+            self.frames.push_back(delta);
+        }
+        self.latest = state;
+
+        while self.used > self.budget {
+            match self.frames.pop_front() {
+                Some(old) => self.used -= old.len(),
+                None => break,
+            }
+        }
+    }
+
+    // Step back one frame, returns the state to load or None once history runs out
+    pub fn pop(&mut self) -> Option<&[u8]> {
+        let delta = self.frames.pop_back()?;
+        self.used -= delta.len();
+        match decode(&self.latest, &delta) {
+            Some(state) => self.latest = state,
+            None => {
+                self.clear();
+                return None;
+            },
+        }
+        Some(&self.latest)
+    }
+
+    // Frames that can be stepped back
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Bytes held by the deltas, kept under the budget
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    // Forget everything, e.g. after loading a save state
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.latest.clear();
+        self.used = 0;
+    }
+}
+
+// Delta that turns `to` back into `from`:
+//   tag 0 + RLE(from ^ to) when both are the same length
+//   tag 1 + from when they aren't (mode switch changed the RAM size)
+fn encode(to: &[u8], from: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if to.len() != from.len() {
+        out.push(1);
+        out.extend_from_slice(from);
+        return out;
+    }
+    out.push(0);
+
+    // Runs of (unchanged byte count, changed byte count, changed bytes XORed)
+    let mut i = 0;
+    while i < to.len() {
+        let same_start = i;
+        while i < to.len() && to[i] == from[i] {
+            i += 1;
+        }
+        let diff_start = i;
+        while i < to.len() && to[i] != from[i] {
+            i += 1;
+        }
+        write_varint(&mut out, diff_start - same_start);
+        write_varint(&mut out, i - diff_start);
+        out.extend(to[diff_start..i].iter().zip(&from[diff_start..i]).map(|(a, b)| a ^ b));
+    }
+    out
+}
+
+// Apply a delta from encode to `to`, giving back `from`. None if the delta doesn't fit `to`
+fn decode(to: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    match delta.first()? {
+        0 => (),
+        1 => return Some(delta[1..].to_vec()),
+        _ => return None,
+    }
+
+    let mut out = to.to_vec();
+    let mut pos = 1;
+    let mut i: usize = 0;
+    while pos < delta.len() {
+        i = i.checked_add(read_varint(delta, &mut pos)?)?;
+        let len = read_varint(delta, &mut pos)?;
+        let bytes = delta.get(pos..pos.checked_add(len)?)?;
+        for (byte, x) in out.get_mut(i..i.checked_add(len)?)?.iter_mut().zip(bytes) {
+            *byte ^= x;
+        }
+        pos += len;
+        i += len;
+    }
+    Some(out)
+}
+
+// LEB128, 7 bits at a time with the high bit marking more to come
+fn write_varint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut v = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        if shift >= usize::BITS {
+            return None;
+        }
+        v |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(v);
+        }
+        shift += 7;
+    }
+}
+
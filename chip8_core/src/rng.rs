@@ -0,0 +1,118 @@
+/*
+    Random numbers for CXNN
+
+    The generator is owned by Emu so a seed reproduces a run exactly, and the built-in
+    algorithms expose their full state so save states can capture it.
+ */
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Built-in generators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RngAlgorithm {
+    #[default]
+    ChaCha8,        // Cryptographic quality
+    Xorshift,       // xorshift64*, fast with 8 bytes of state
+}
+
+impl RngAlgorithm {
+    // chacha / chacha8 or xorshift, any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "chacha" | "chacha8" => Some(RngAlgorithm::ChaCha8),
+            "xorshift" => Some(RngAlgorithm::Xorshift),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) enum EmuRng {
+    ChaCha8(Box<ChaCha8Rng>),           // Boxed, it carries a 256 byte output buffer
+    Xorshift(Xorshift64),
+    Custom(Box<dyn RngCore + Send>),    // User supplied, opaque to save states
+}
+
+impl EmuRng {
+    pub(crate) fn new(algorithm: RngAlgorithm, seed: u64) -> Self {
+        match algorithm {
+            RngAlgorithm::ChaCha8 => EmuRng::ChaCha8(Box::new(ChaCha8Rng::seed_from_u64(seed))),
+            RngAlgorithm::Xorshift => EmuRng::Xorshift(Xorshift64::seed_from_u64(seed)),
+        }
+    }
+
+    // None for a custom generator
+    pub(crate) fn algorithm(&self) -> Option<RngAlgorithm> {
+        match self {
+            EmuRng::ChaCha8(_) => Some(RngAlgorithm::ChaCha8),
+            EmuRng::Xorshift(_) => Some(RngAlgorithm::Xorshift),
+            EmuRng::Custom(_) => None,
+        }
+    }
+}
+
+impl RngCore for EmuRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            EmuRng::ChaCha8(rng) => rng.next_u32(),
+            EmuRng::Xorshift(rng) => rng.next_u32(),
+            EmuRng::Custom(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            EmuRng::ChaCha8(rng) => rng.next_u64(),
+            EmuRng::Xorshift(rng) => rng.next_u64(),
+            EmuRng::Custom(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            EmuRng::ChaCha8(rng) => rng.fill_bytes(dest),
+            EmuRng::Xorshift(rng) => rng.fill_bytes(dest),
+            EmuRng::Custom(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// xorshift64* (Vigna), the state must never be zero
+pub(crate) struct Xorshift64(pub(crate) u64);
+
+impl Xorshift64 {
+    fn seed_from_u64(seed: u64) -> Self {
+        // One round of splitmix64 so small seeds still give a well mixed, non-zero state
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self(if z == 0 { 1 } else { z })
+    }
+}
+
+impl RngCore for Xorshift64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_chacha::rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+/*
+    ROM database : settings for known ROMs, looked up by the SHA-1 of the ROM's bytes
+
+    Files use the programs.json layout of the community CHIP-8 database
+    (https://github.com/chip-8/chip-8-database), a list of programs each holding their ROMs by
+    SHA-1:
+
+        [{ "title": "Pong", "roms": { "<sha1>": {
+            "platforms": ["superchip"],
+            "tickrate": 20,
+            "quirkyPlatforms": { "superchip": { "shift": false } },
+            "keys": { "up": 1, "down": 4 },
+            "colors": { "pixels": ["#000000", "#ffcc00"] }
+        } } }]
+
+    The first platform listed picks the mode, its quirks and its default tickrate (instructions
+    per frame), quirkyPlatforms adjusts those quirks. Fields we have no use for are skipped.
+
+    The bundled database is data/programs.json. Replacing it with the community programs.json
+    needs no code changes; the copy in the tree covers the ROMs in tests/roms. Local files are
+    layered on top with extend, see local_paths. Entries there win field by field, so an override
+    only has to name what it changes.
+ */
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::clock::TIMER_HZ;
+use crate::movie::sha1_hex;
+use crate::screenshot::{Palette, DEFAULT_PALETTE};
+use crate::{DatabaseError, Mode, Quirks};
+
+const BUNDLED: &str = include_str!("../data/programs.json");
+const LOCAL_FILE: &str = "programs.json";
+
+// Settings for one ROM, None where the database doesn't say
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RomConfig {
+    pub title: Option<String>,
+    pub mode: Option<Mode>,
+    pub quirks: Option<Quirks>,
+    pub ips: Option<u32>,
+    pub keys: Vec<(String, u8)>,    // Control name (up, down, left, right, a, b, ...) and the keypad key it presses
+    pub palette: Option<Palette>,
+}
+
+impl RomConfig {
+    // Take every setting over has
+    fn apply(&mut self, over: RomConfig) {
+        self.title = over.title.or(self.title.take());
+        self.mode = over.mode.or(self.mode);
+        self.quirks = over.quirks.or(self.quirks);
+        self.ips = over.ips.or(self.ips);
+        if !over.keys.is_empty() {
+            self.keys = over.keys;
+        }
+        self.palette = over.palette.or(self.palette);
+    }
+
+    // Keypad key for a control name
+    pub fn key(&self, control: &str) -> Option<u8> {
+        self.keys.iter().find(|(name, _)| name == control).map(|&(_, key)| key)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, RomConfig>,   // By lowercase SHA-1
+}
+
+impl RomDatabase {
+    // The database that ships with the core
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED).expect("Bundled ROM database is valid")
+    }
+
+    pub fn parse(json: &str) -> Result<Self, DatabaseError> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|e| DatabaseError::Json { line: e.line(), message: e.to_string() })?;
+        let programs = value.as_array().ok_or_else(|| format_error("expected a list of programs"))?;
+
+        let mut db = Self::default();
+        for program in programs {
+            let title = program.get("title").and_then(Value::as_str);
+            let roms = program.get("roms").and_then(Value::as_object)
+                .ok_or_else(|| format_error(&format!("{} has no roms", title.unwrap_or("a program"))))?;
+            for (hash, rom) in roms {
+                let rom = rom.as_object().ok_or_else(|| format_error(&format!("ROM {} isn't an object", hash)))?;
+                let mut config = parse_rom(rom).map_err(|message| format_error(&format!("ROM {}: {}", hash, message)))?;
+                config.title = title.map(str::to_string);
+                db.roms.entry(hash.to_ascii_lowercase()).or_default().apply(config);
+            }
+        }
+        Ok(db)
+    }
+
+    // Layer other on top, its settings win
+    pub fn extend(&mut self, other: RomDatabase) {
+        for (hash, config) in other.roms {
+            self.roms.entry(hash).or_default().apply(config);
+        }
+    }
+
+    // Settings for the bytes that are about to go to Emu::load
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomConfig> {
+        self.get(&sha1_hex(rom))
+    }
+
+    pub fn get(&self, sha1: &str) -> Option<&RomConfig> {
+        self.roms.get(&sha1.to_ascii_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
+
+// Local database files frontends pick up by themselves, lowest priority first:
+// chip8/programs.json in the user's config directory, then programs.json next to the ROM.
+// The files don't have to exist
+pub fn local_paths(rom_path: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = config_dir().map(|dir| dir.join("chip8").join(LOCAL_FILE)).into_iter().collect();
+    paths.push(rom_path.with_file_name(LOCAL_FILE));
+    paths
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        var("APPDATA")
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    }
+}
+
+fn format_error(message: &str) -> DatabaseError {
+    DatabaseError::Format(message.to_string())
+}
+
+fn parse_rom(rom: &Map<String, Value>) -> Result<RomConfig, String> {
+    let mut config = RomConfig::default();
+
+    // First platform we can run decides the machine
+    let platform = match rom.get("platforms") {
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).find_map(platform),
+        Some(_) => return Err("platforms isn't a list".to_string()),
+        None => None,
+    };
+    if let Some((id, mode, quirks, tickrate)) = platform {
+        let mut quirks = quirks;
+        if let Some(changes) = rom.get("quirkyPlatforms").and_then(|q| q.get(id)).and_then(Value::as_object) {
+            apply_quirks(&mut quirks, changes)?;
+        }
+        config.mode = Some(mode);
+        config.quirks = Some(quirks);
+        config.ips = Some(tickrate * TIMER_HZ);
+    }
+
+    if let Some(tickrate) = rom.get("tickrate") {
+        let tickrate = tickrate.as_u64().filter(|&t| t > 0).and_then(|t| u32::try_from(t).ok())
+            .ok_or_else(|| format!("tickrate {} isn't a positive whole number", tickrate))?;
+        config.ips = Some(tickrate.saturating_mul(TIMER_HZ));
+    }
+
+    if let Some(keys) = rom.get("keys") {
+        let keys = keys.as_object().ok_or("keys isn't an object")?;
+        for (name, key) in keys {
+            let key = key.as_u64().filter(|&k| k < 16).ok_or_else(|| format!("bad key for {}", name))?;
+            config.keys.push((name.clone(), key as u8));
+        }
+    }
+
+    if let Some(pixels) = rom.get("colors").and_then(|c| c.get("pixels")) {
+        let pixels = pixels.as_array().ok_or("colors.pixels isn't a list")?;
+        let mut palette = DEFAULT_PALETTE;
+        for (entry, color) in palette.iter_mut().zip(pixels) {
+            *entry = color.as_str().and_then(parse_color).ok_or_else(|| format!("bad color {}", color))?;
+        }
+        config.palette = Some(palette);
+    }
+    Ok(config)
+}
+
+// Platform ids from the database's platforms.json, with their mode, quirks and default
+// tickrate. CHIP-48 has no SUPER-CHIP opcodes, so it runs as CHIP-8 with its quirks. MegaChip
+// isn't supported, so ROMs only listed for it get no machine settings
+fn platform(id: &str) -> Option<(&str, Mode, Quirks, u32)> {
+    let modern = Quirks { vf_reset: false, memory: true, shift: false, jump: false, clip: true };
+    let (id, mode, quirks, tickrate) = match id {
+        "originalChip8" => ("originalChip8", Mode::Chip8, Quirks::vip(), 15),
+        "hybridVIP" => ("hybridVIP", Mode::Chip8, Quirks::vip(), 15),
+        "chip8x" => ("chip8x", Mode::Chip8, Quirks::vip(), 15),
+        "modernChip8" => ("modernChip8", Mode::Chip8, modern, 12),
+        "chip48" => ("chip48", Mode::Chip8, Quirks::chip48(), 30),
+        "superchip1" => ("superchip1", Mode::SuperChip, Quirks::schip(), 30),
+        "superchip" => ("superchip", Mode::SuperChip, Quirks::schip(), 30),
+        "xochip" => ("xochip", Mode::XoChip, Quirks::xochip(), 100),
+        _ => return None,
+    };
+    Some((id, mode, quirks, tickrate))
+}
+
+// Quirk names as the database spells them. memoryIncrementByX (CHIP-48's I += X) is the
+// closest to leaving I alone of what we can do. vblank has no counterpart here
+fn apply_quirks(quirks: &mut Quirks, changes: &Map<String, Value>) -> Result<(), String> {
+    for (name, value) in changes {
+        let on = value.as_bool().ok_or_else(|| format!("quirk {} isn't true or false", name))?;
+        match name.as_str() {
+            "logic" => quirks.vf_reset = on,
+            "memoryLeaveIUnchanged" | "memoryIncrementByX" => quirks.memory = !on,
+            "shift" => quirks.shift = on,
+            "jump" => quirks.jump = on,
+            "wrap" => quirks.clip = !on,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+// "#rrggbb"
+fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
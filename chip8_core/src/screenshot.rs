@@ -0,0 +1,141 @@
+/*
+    Screenshots : encode the current screen as PNG or PBM
+
+    PNG keeps colors, each screen cell's plane mask picks an entry of the palette. PBM is
+    black and white, any lit plane is a 1. PBM output is the plain (P1) variant with one
+    line per row so golden images read and diff as text.
+ */
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::Emu;
+
+pub type Palette = [(u8, u8, u8); 16];
+
+// Color for each plane mask a pixel can hold. Plain CHIP-8 only ever uses the first two
+pub const DEFAULT_PALETTE: Palette = [
+    (0, 0, 0),          // Off
+    (255, 255, 255),    // Plane 1
+    (170, 170, 170),    // Plane 2
+    (85, 85, 85),       // Planes 1 + 2
+    (255, 85, 85),
+    (255, 170, 85),
+    (255, 255, 85),
+    (85, 255, 85),
+    (85, 255, 255),
+    (85, 170, 255),
+    (85, 85, 255),
+    (170, 85, 255),
+    (255, 85, 255),
+    (255, 85, 170),
+    (170, 255, 170),
+    (170, 170, 255),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Pbm,
+}
+
+impl ImageFormat {
+    // png or pbm in any case, which is also how from_path reads an extension
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "pbm" => Some(ImageFormat::Pbm),
+            _ => None,
+        }
+    }
+
+    // Format named by a file's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Pbm => "pbm",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Screenshot {
+    pub format: ImageFormat,
+    pub scale: u32,             // Image pixels per screen pixel, each way
+    pub palette: Palette,       // Ignored by PBM
+}
+
+impl Default for Screenshot {
+    fn default() -> Self {
+        Self {
+            format: ImageFormat::Png,
+            scale: 1,
+            palette: DEFAULT_PALETTE,
+        }
+    }
+}
+
+impl Screenshot {
+    // One bit per screen pixel, what golden images are stored as
+    pub fn pbm() -> Self {
+        Self { format: ImageFormat::Pbm, ..Self::default() }
+    }
+
+    pub fn write<W: Write>(&self, emu: &Emu, out: W) -> io::Result<()> {
+        match self.format {
+            ImageFormat::Png => self.write_png(emu, out),
+            ImageFormat::Pbm => self.write_pbm(emu, out),
+        }
+    }
+
+    // Encoded image in memory, handy for comparing against a golden file
+    pub fn encode(&self, emu: &Emu) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write(emu, &mut data).expect("Writing to a Vec can't fail");
+        data
+    }
+
+    // Indexed PNG, the palette goes in as is
+    fn write_png<W: Write>(&self, emu: &Emu, out: W) -> io::Result<()> {
+        let (width, height) = self.size(emu);
+        let colors: Vec<u8> = self.palette.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+        let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(colors);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        let pixels: Vec<u8> = self.scaled(emu).map(|px| px % self.palette.len() as u8).collect();
+        writer.write_image_data(&pixels).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    fn write_pbm<W: Write>(&self, emu: &Emu, mut out: W) -> io::Result<()> {
+        let (width, height) = self.size(emu);
+        writeln!(out, "P1")?;
+        writeln!(out, "{} {}", width, height)?;
+        let bits: Vec<u8> = self.scaled(emu).map(|px| if px == 0 { b'0' } else { b'1' }).collect();
+        for row in bits.chunks(width.max(1)) {
+            out.write_all(row)?;
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+
+    fn size(&self, emu: &Emu) -> (usize, usize) {
+        let scale = self.scale as usize;
+        (emu.display_width() * scale, emu.display_height() * scale)
+    }
+
+    // Plane masks in image order, each screen pixel repeated scale times each way
+    fn scaled<'a>(&self, emu: &'a Emu) -> impl Iterator<Item = u8> + 'a {
+        let scale = self.scale as usize;
+        emu.get_display()
+            .chunks(emu.display_width())
+            .flat_map(move |row| std::iter::repeat_n(row, scale))
+            .flat_map(move |row| row.iter().flat_map(move |&px| std::iter::repeat_n(px, scale)))
+    }
+}
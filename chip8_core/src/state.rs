@@ -0,0 +1,299 @@
+/*
+    Save states : versioned binary snapshot of the whole machine
+
+    Layout (little endian):
+        magic "C8ST", version u16, then the fields in the order save_state writes them
+
+    Versions:
+        1   RNG was always ChaCha8 and its seed wasn't recorded
+        2   RNG algorithm tag and seed
+ */
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::rng::{EmuRng, Xorshift64};
+use crate::*;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u16 = 2;     // Bump when the layout changes and teach load_state to migrate the old one
+
+// RNG tags from version 2 on
+const RNG_CHACHA8: u8 = 0;
+const RNG_XORSHIFT: u8 = 1;
+const RNG_CUSTOM: u8 = 2;
+
+impl Emu {
+    // Snapshot the full machine state
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::with_capacity(self.ram.len() + self.screen.len() + 256));
+        w.bytes(MAGIC);
+        w.u16(VERSION);
+
+        w.u8(mode_to_byte(self.mode));
+        w.u8(quirks_to_byte(self.quirks));
+        w.u16(self.pc);
+        w.u16(self.i_reg);
+        w.u16(self.sp);
+        w.u8(self.dt);
+        w.u8(self.st);
+        w.bytes(&self.v_reg);
+        for addr in self.stack {
+            w.u16(addr);
+        }
+        w.u16(keys_to_bits(&self.keys));
+        w.u8(self.hires as u8);
+        w.u8(self.exited as u8);
+        w.u8(self.planes);
+        w.u8(self.pitch);
+        w.bytes(&self.pattern);
+        w.bytes(&self.rpl);
+
+        // RNG: ChaCha is rebuilt from key, stream and position, xorshift is its one word
+        w.u64(self.seed);
+        match &self.rng {
+            EmuRng::ChaCha8(rng) => {
+                w.u8(RNG_CHACHA8);
+                w.bytes(&rng.get_seed());
+                w.u64(rng.get_stream());
+                w.u128(rng.get_word_pos());
+            },
+            EmuRng::Xorshift(rng) => {
+                w.u8(RNG_XORSHIFT);
+                w.u64(rng.0);
+            },
+            EmuRng::Custom(_) => w.u8(RNG_CUSTOM),
+        }
+
+        w.u32(self.ram.len() as u32);
+        w.bytes(&self.ram);
+        w.bytes(&self.screen);
+
+        w.0
+    }
+
+    // Restore a snapshot from save_state, older versions are migrated. The emulator is left untouched if this fails
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut r = Reader(data);
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = r.u16()?;
+        if version == 0 || version > VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let mode = mode_from_byte(r.u8()?)?;
+        let quirks = quirks_from_byte(r.u8()?);
+        let pc = r.u16()?;
+        let i_reg = r.u16()?;
+        let sp = r.u16()?;
+        if sp as usize > STACK_SIZE {
+            return Err(StateError::Invalid("stack pointer"));
+        }
+        let dt = r.u8()?;
+        let st = r.u8()?;
+        let mut v_reg = [0; NUM_REGS];
+        v_reg.copy_from_slice(r.take(NUM_REGS)?);
+        let mut stack = [0; STACK_SIZE];
+        for addr in stack.iter_mut() {
+            *addr = r.u16()?;
+        }
+        let keys = keys_from_bits(r.u16()?);
+        let hires = r.bool()?;
+        let exited = r.bool()?;
+        let planes = r.u8()?;
+        let pitch = r.u8()?;
+        if hires && mode == Mode::Chip8 {
+            return Err(StateError::Invalid("resolution"));
+        }
+        if planes >= 1 << NUM_PLANES || (planes != 1 && mode != Mode::XoChip) {
+            return Err(StateError::Invalid("plane mask"));
+        }
+        if pitch != DEFAULT_PITCH && mode != Mode::XoChip {
+            return Err(StateError::Invalid("pitch"));
+        }
+        let mut pattern = [0; PATTERN_SIZE];
+        pattern.copy_from_slice(r.take(PATTERN_SIZE)?);
+        let mut rpl = [0; NUM_RPL_FLAGS];
+        rpl.copy_from_slice(r.take(NUM_RPL_FLAGS)?);
+
+        let (seed, rng) = match version {
+            1 => (0, Some(read_chacha(&mut r)?)),
+            _ => {
+                let seed = r.u64()?;
+                let rng = match r.u8()? {
+                    RNG_CHACHA8 => Some(read_chacha(&mut r)?),
+                    RNG_XORSHIFT => Some(EmuRng::Xorshift(Xorshift64(r.u64()?))),
+                    RNG_CUSTOM => None,     // Whatever generator is plugged in now carries on
+                    _ => return Err(StateError::Invalid("RNG algorithm")),
+                };
+                (seed, rng)
+            },
+        };
+
+        let ram_len = r.u32()? as usize;
+        if ram_len != mode.ram_size() {
+            return Err(StateError::Invalid("memory size"));
+        }
+        let ram = r.take(ram_len)?.to_vec();
+        let mut screen = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        screen.copy_from_slice(r.take(HIRES_WIDTH * HIRES_HEIGHT)?);
+        if !r.0.is_empty() {
+            return Err(StateError::Invalid("length"));
+        }
+
+        // Everything parsed, commit
+        self.mode = mode;
+        self.quirks = quirks;
+        self.pc = pc;
+        self.i_reg = i_reg;
+        self.sp = sp;
+        self.dt = dt;
+        self.st = st;
+        self.v_reg = v_reg;
+        self.stack = stack;
+        self.keys = keys;
+        self.hires = hires;
+        self.exited = exited;
+        self.planes = planes;
+        self.pitch = pitch;
+        self.pattern = pattern;
+        self.rpl = rpl;
+        self.seed = seed;
+        if let Some(rng) = rng {
+            self.rng = rng;
+        }
+        self.ram = ram;
+        self.screen = screen;
+        Ok(())
+    }
+}
+
+fn read_chacha(r: &mut Reader) -> Result<EmuRng, StateError> {
+    let mut key = [0; 32];
+    key.copy_from_slice(r.take(32)?);
+    let mut rng = ChaCha8Rng::from_seed(key);
+    rng.set_stream(r.u64()?);
+    rng.set_word_pos(r.u128()?);
+    Ok(EmuRng::ChaCha8(Box::new(rng)))
+}
+
+pub(crate) fn mode_to_byte(mode: Mode) -> u8 {
+    match mode {
+        Mode::Chip8 => 0,
+        Mode::SuperChip => 1,
+        Mode::XoChip => 2,
+    }
+}
+
+pub(crate) fn mode_from_byte(byte: u8) -> Result<Mode, StateError> {
+    match byte {
+        0 => Ok(Mode::Chip8),
+        1 => Ok(Mode::SuperChip),
+        2 => Ok(Mode::XoChip),
+        _ => Err(StateError::Invalid("mode")),
+    }
+}
+
+fn quirks_to_byte(q: Quirks) -> u8 {
+    (q.vf_reset as u8)
+        | (q.memory as u8) << 1
+        | (q.shift as u8) << 2
+        | (q.jump as u8) << 3
+        | (q.clip as u8) << 4
+}
+
+fn quirks_from_byte(byte: u8) -> Quirks {
+    Quirks {
+        vf_reset: byte & 1 != 0,
+        memory: byte & (1 << 1) != 0,
+        shift: byte & (1 << 2) != 0,
+        jump: byte & (1 << 3) != 0,
+        clip: byte & (1 << 4) != 0,
+    }
+}
+
+fn keys_to_bits(keys: &[bool; NUM_KEYS]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .fold(0, |bits, (i, &pressed)| bits | (pressed as u16) << i)
+}
+
+fn keys_from_bits(bits: u16) -> [bool; NUM_KEYS] {
+    let mut keys = [false; NUM_KEYS];
+    for (i, key) in keys.iter_mut().enumerate() {
+        *key = bits & (1 << i) != 0;
+    }
+    keys
+}
+
+// Little endian byte sink
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, b: &[u8]) {
+        self.0.extend_from_slice(b);
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn u128(&mut self, v: u128) {
+        self.bytes(&v.to_le_bytes());
+    }
+}
+
+// Little endian byte source, every read fails with Truncated past the end
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], StateError> {
+        if self.0.len() < n {
+            return Err(StateError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("flag")),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u128(&mut self) -> Result<u128, StateError> {
+        Ok(u128::from_le_bytes(self.take(16)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,174 @@
+/*
+    Execution tracer : one record per executed instruction
+
+    Opt-in through Emu::set_tracer. Text traces are one line per instruction, meant for diff:
+
+        cycle  pc    op    disassembly           registers and I before -> after
+
+    Binary traces are a "C8TR" header and a version, then fixed size little endian records
+    (see TraceRecord::write_binary). read_binary turns them back into records.
+ */
+use std::io::{self, ErrorKind, Write};
+
+use crate::disasm::{disassemble, Syntax};
+use crate::state::{mode_from_byte, mode_to_byte};
+use crate::Mode;
+
+const MAGIC: &[u8; 4] = b"C8TR";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 6;
+const RECORD_SIZE: usize = 51;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub cycle: u64,             // Instructions traced before this one
+    pub pc: u16,
+    pub opcode: u16,
+    pub next: u16,              // Word after the opcode, the address of F000 NNNN
+    pub mode: Mode,             // Instruction set the opcode ran under
+    pub v_before: [u8; 16],
+    pub i_before: u16,
+    pub v_after: [u8; 16],
+    pub i_after: u16,
+}
+
+impl TraceRecord {
+    pub fn disassembly(&self, syntax: Syntax) -> String {
+        let bytes = [self.opcode.to_be_bytes(), self.next.to_be_bytes()].concat();
+        disassemble(&bytes, self.pc, self.mode, syntax)
+            .first()
+            .map(|line| line.to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn write_text<W: Write>(&self, out: &mut W, syntax: Syntax) -> io::Result<()> {
+        writeln!(out, "{:>10} {:04X}: {:04X}  {:<22} V={} I={:04X} -> V={} I={:04X}",
+            self.cycle, self.pc, self.opcode, self.disassembly(syntax),
+            hex(&self.v_before), self.i_before, hex(&self.v_after), self.i_after)
+    }
+
+    // cycle u64, pc u16, opcode u16, next u16, mode u8, V before [16], I before u16, V after [16], I after u16
+    pub fn write_binary<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut record = Vec::with_capacity(RECORD_SIZE);
+        record.extend_from_slice(&self.cycle.to_le_bytes());
+        record.extend_from_slice(&self.pc.to_le_bytes());
+        record.extend_from_slice(&self.opcode.to_le_bytes());
+        record.extend_from_slice(&self.next.to_le_bytes());
+        record.push(mode_to_byte(self.mode));
+        record.extend_from_slice(&self.v_before);
+        record.extend_from_slice(&self.i_before.to_le_bytes());
+        record.extend_from_slice(&self.v_after);
+        record.extend_from_slice(&self.i_after.to_le_bytes());
+        out.write_all(&record)
+    }
+
+    fn read_binary(data: &[u8]) -> Option<Self> {
+        let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+        Some(TraceRecord {
+            cycle: u64::from_le_bytes(data[0..8].try_into().ok()?),
+            pc: u16_at(8),
+            opcode: u16_at(10),
+            next: u16_at(12),
+            mode: mode_from_byte(data[14]).ok()?,
+            v_before: data[15..31].try_into().ok()?,
+            i_before: u16_at(31),
+            v_after: data[33..49].try_into().ok()?,
+            i_after: u16_at(49),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    #[default]
+    Text,
+    Binary,
+}
+
+impl TraceFormat {
+    // text, or binary / bin for the fixed size records
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Some(TraceFormat::Text),
+            "binary" | "bin" => Some(TraceFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+// Sink for trace records. Write errors stop the trace and come back from finish
+pub struct Tracer {
+    out: Box<dyn Write + Send>,
+    format: TraceFormat,
+    syntax: Syntax,
+    cycle: u64,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write + Send>, format: TraceFormat, syntax: Syntax) -> io::Result<Self> {
+        let mut tracer = Self {
+            out,
+            format,
+            syntax,
+            cycle: 0,
+            error: None,
+        };
+        if format == TraceFormat::Binary {
+            tracer.out.write_all(MAGIC)?;
+            tracer.out.write_all(&VERSION.to_le_bytes())?;
+        }
+        Ok(tracer)
+    }
+
+    // Instructions recorded so far
+    pub fn cycles(&self) -> u64 {
+        self.cycle
+    }
+
+    // Called by Emu::tick, cycle is filled in here
+    pub(crate) fn record(&mut self, mut record: TraceRecord) {
+        record.cycle = self.cycle;
+        self.cycle += 1;
+        if self.error.is_some() {
+            return;
+        }
+        let result = match self.format {
+            TraceFormat::Text => record.write_text(&mut self.out, self.syntax),
+            TraceFormat::Binary => record.write_binary(&mut self.out),
+        };
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    // Flush, reporting the first write error if there was one
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()
+    }
+}
+
+// Parse a binary trace
+pub fn read_binary(data: &[u8]) -> io::Result<Vec<TraceRecord>> {
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+    if data.len() < HEADER_SIZE || &data[..4] != MAGIC {
+        return Err(invalid("not a binary trace"));
+    }
+    if u16::from_le_bytes([data[4], data[5]]) != VERSION {
+        return Err(invalid("unsupported trace version"));
+    }
+    let body = &data[HEADER_SIZE..];
+    if !body.len().is_multiple_of(RECORD_SIZE) {
+        return Err(invalid("trace is truncated"));
+    }
+    body.chunks(RECORD_SIZE)
+        .map(|chunk| TraceRecord::read_binary(chunk).ok_or_else(|| invalid("trace has an invalid record")))
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
@@ -0,0 +1,171 @@
+/*
+    Video recording : one frame per 60Hz timer tick, as an animated GIF or a Y4M stream
+
+    Frontends call Recorder::frame each time they call tick_timers. Every frame is the size of
+    the high resolution screen times scale, low resolution frames are doubled up to fit, so a
+    recording can switch modes without the video changing size.
+
+    GIF delays are in hundredths of a second, and viewers slow anything under 2 hundredths right
+    down, so GIFs run at 50Hz: each frame's delay is a whole number of 50ths, a frame too short
+    to reach the next 50th is dropped, and runs of identical frames are merged into one longer
+    frame. Playback keeps to real time.
+    Y4M is uncompressed limited range 4:4:4 at 60 frames per second, for piping into other tools.
+ */
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::screenshot::Palette;
+use crate::{Emu, HIRES_HEIGHT, HIRES_WIDTH};
+
+const FRAME_RATE: u64 = 60;
+const GIF_FRAME_RATE: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VideoFormat {
+    #[default]
+    Gif,
+    Y4m,
+}
+
+impl VideoFormat {
+    // gif or y4m in any case, which is also how from_path reads an extension
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "gif" => Some(VideoFormat::Gif),
+            "y4m" => Some(VideoFormat::Y4m),
+            _ => None,
+        }
+    }
+
+    // Format named by a file's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+enum Encoder {
+    Gif {
+        encoder: gif::Encoder<Box<dyn Write + Send>>,
+        pending: Option<(Vec<u8>, u64)>,    // Last frame and how many ticks it has lasted, written once it changes
+    },
+    Y4m {
+        out: Box<dyn Write + Send>,
+        yuv: Vec<[u8; 3]>,                  // Palette converted to Y, Cb, Cr
+    },
+}
+
+pub struct Recorder {
+    encoder: Encoder,
+    scale: usize,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn new(out: Box<dyn Write + Send>, format: VideoFormat, scale: u32, palette: &Palette) -> io::Result<Self> {
+        let scale = scale.max(1) as usize;
+        let (width, height) = (HIRES_WIDTH * scale, HIRES_HEIGHT * scale);
+        let encoder = match format {
+            VideoFormat::Gif => {
+                let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "GIF frames are at most 65535 pixels wide");
+                let colors: Vec<u8> = palette.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+                let mut encoder = gif::Encoder::new(out, width.try_into().map_err(|_| too_big())?,
+                    height.try_into().map_err(|_| too_big())?, &colors).map_err(io::Error::other)?;
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                Encoder::Gif { encoder, pending: None }
+            },
+            VideoFormat::Y4m => {
+                let mut out = out;
+                writeln!(out, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", width, height, FRAME_RATE)?;
+                Encoder::Y4m { out, yuv: palette.iter().map(|&(r, g, b)| to_yuv(r, g, b)).collect() }
+            },
+        };
+        Ok(Self { encoder, scale, frames: 0 })
+    }
+
+    // Frames recorded so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // Record the screen as it is now, call once per tick_timers
+    pub fn frame(&mut self, emu: &Emu) -> io::Result<()> {
+        let pixels = self.pixels(emu);
+        self.frames += 1;
+        match &mut self.encoder {
+            Encoder::Gif { encoder, pending } => match pending {
+                Some((last, ticks)) if *last == pixels => *ticks += 1,
+                _ => {
+                    if let Some((last, ticks)) = pending.take() {
+                        write_gif_frame(encoder, &last, self.scale, self.frames - 1 - ticks, ticks, false)?;
+                    }
+                    *pending = Some((pixels, 1));
+                },
+            },
+            Encoder::Y4m { out, yuv } => {
+                out.write_all(b"FRAME\n")?;
+                let colors: Vec<[u8; 3]> = pixels.iter().map(|&px| yuv[px as usize % yuv.len()]).collect();
+                for channel in 0..3 {
+                    let plane: Vec<u8> = colors.iter().map(|color| color[channel]).collect();
+                    out.write_all(&plane)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    // Write out anything held back and flush
+    pub fn finish(self) -> io::Result<()> {
+        match self.encoder {
+            Encoder::Gif { mut encoder, pending } => {
+                if let Some((last, ticks)) = pending {
+                    write_gif_frame(&mut encoder, &last, self.scale, self.frames - ticks, ticks, true)?;
+                }
+                encoder.into_inner()?.flush()
+            },
+            Encoder::Y4m { mut out, .. } => out.flush(),
+        }
+    }
+
+    // Plane masks at the recording size, low resolution pixels take up twice the space
+    fn pixels(&self, emu: &Emu) -> Vec<u8> {
+        let width = emu.display_width();
+        let scale = self.scale * HIRES_WIDTH / width;
+        let display = emu.get_display();
+        let mut pixels = Vec::with_capacity(HIRES_WIDTH * HIRES_HEIGHT * self.scale * self.scale);
+        for row in display.chunks(width) {
+            let line: Vec<u8> = row.iter().flat_map(|&px| std::iter::repeat_n(px, scale)).collect();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+        pixels
+    }
+}
+
+// Frame starting at tick start that lasts ticks ticks. Delays come from rounding each
+// frame's start and end to 50ths, so they add up to real time. A frame that rounds to nothing
+// is dropped, apart from the last one which is always shown
+fn write_gif_frame(encoder: &mut gif::Encoder<Box<dyn Write + Send>>, pixels: &[u8], scale: usize, start: u64, ticks: u64, last: bool) -> io::Result<()> {
+    let hundredths = |tick: u64| (tick * GIF_FRAME_RATE + FRAME_RATE / 2) / FRAME_RATE * (100 / GIF_FRAME_RATE);
+    let mut delay = hundredths(start + ticks) - hundredths(start);
+    if delay == 0 {
+        if !last {
+            return Ok(());
+        }
+        delay = 100 / GIF_FRAME_RATE;
+    }
+    let mut frame = gif::Frame::from_indexed_pixels((HIRES_WIDTH * scale) as u16, (HIRES_HEIGHT * scale) as u16, pixels, None);
+    frame.delay = delay.min(u16::MAX as u64) as u16;
+    encoder.write_frame(&frame).map_err(io::Error::other)
+}
+
+// Limited range BT.601 (Y 16 - 235, Cb and Cr 16 - 240), what players assume for a Y4M stream
+// that doesn't tag its range
+fn to_yuv(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let cb = (b - y) * 0.564;
+    let cr = (r - y) * 0.713;
+    [16.0 + y * 219.0 / 255.0, 128.0 + cb * 224.0 / 255.0, 128.0 + cr * 224.0 / 255.0]
+        .map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
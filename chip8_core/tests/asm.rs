@@ -0,0 +1,105 @@
+use chip8_core::asm::{assemble, assemble_file};
+use chip8_core::disasm::{disassemble, Syntax};
+use chip8_core::*;
+use std::fs;
+
+#[test]
+fn assembles_labels_constants_and_data() {
+    let source = "
+        SPEED   EQU 2               ; constant
+        start:  LD V0, SPEED
+                LD I, sprite
+                DRW V0, V1, 5
+        loop:   JP loop
+        sprite: DB 0x20, $60, #20, 32, 0b01110000
+                DW start + 2, -1
+    ";
+    let rom = assemble(source).unwrap();
+    assert_eq!(rom, [
+        0x60, 0x02,
+        0xA2, 0x08,
+        0xD0, 0x15,
+        0x12, 0x06,
+        0x20, 0x60, 0x20, 0x20, 0x70,
+        0x02, 0x02, 0xFF, 0xFF,
+    ]);
+}
+
+#[test]
+fn forward_references_resolve() {
+    let rom = assemble("CALL sub\nsub: RET").unwrap();
+    assert_eq!(rom, [0x22, 0x02, 0x00, 0xEE]);
+}
+
+#[test]
+fn disassembly_round_trips() {
+    // Every opcode, in chunks that fit in memory. Data words come out as DW and go back in as-is
+    let words: Vec<u16> = (0..=u16::MAX).collect();
+    for chunk in words.chunks(0x1000) {
+        let rom: Vec<u8> = chunk.iter().flat_map(|w| w.to_be_bytes()).collect();
+        for mode in [Mode::Chip8, Mode::SuperChip, Mode::XoChip] {
+            let listing: Vec<String> = disassemble(&rom, START_ADDR, mode, Syntax::Cowgod)
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            assert_eq!(assemble(&listing.join("\n")).unwrap(), rom);
+        }
+    }
+}
+
+#[test]
+fn includes_are_relative_to_the_including_file() {
+    let dir = std::env::temp_dir().join(format!("chip8_asm_{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("main.asm"), "JP done\nINCLUDE \"lib/data.asm\"\ndone: EXIT\n").unwrap();
+    fs::write(dir.join("lib/data.asm"), "DB 1, 2\n").unwrap();
+    fs::write(dir.join("loop.asm"), "INCLUDE \"loop.asm\"\n").unwrap();
+
+    let rom = assemble_file(&dir.join("main.asm"));
+    let cycle = assemble_file(&dir.join("loop.asm")).unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(rom.unwrap(), [0x12, 0x04, 0x01, 0x02, 0x00, 0xFD]);
+    assert_eq!((cycle.line, cycle.column), (1, 9));
+    assert!(cycle.message.contains("includes itself"));
+}
+
+#[test]
+fn errors_point_at_line_and_column() {
+    let err = |source: &str| {
+        let e = assemble(source).unwrap_err();
+        (e.line, e.column, e.message)
+    };
+
+    assert_eq!(err("CLS\n  FOO V0"), (2, 3, "unknown instruction `FOO`".to_string()));
+    assert_eq!(err("LD V0, missing"), (1, 8, "undefined symbol `missing`".to_string()));
+    assert_eq!(err("ADD V1,  0x100"), (1, 10, "256 doesn't fit in 8 bits".to_string()));
+    assert_eq!(err("DRW V0, V1"), (1, 1, "invalid operands for DRW".to_string()));
+    assert_eq!(err("a: CLS\na: RET"), (2, 1, "`a` is already defined".to_string()));
+    assert_eq!(err("X EQU 1 +"), (1, 10, "expected a value".to_string()));
+    assert_eq!(err("JP 12z"), (1, 4, "invalid number `12z`".to_string()));
+    assert_eq!(assemble("SCD 16").unwrap_err().to_string(), "<source>:1:5: 16 doesn't fit in 4 bits");
+}
+
+#[test]
+fn huge_values_are_errors() {
+    let err = |source: &str| {
+        let e = assemble(source).unwrap_err();
+        (e.line, e.column, e.message)
+    };
+
+    let max = i64::MAX;
+    assert_eq!(err(&format!("LD V0, {} + 1", max)), (1, 8, "value out of range".to_string()));
+    assert_eq!(err(&format!("X EQU -{} - 2\nLD V0, -X", max)), (1, 7, "value out of range".to_string()));
+    assert_eq!(err(&format!("X EQU -{} - 1\nLD V0, -X", max)), (2, 8, "value out of range".to_string()));
+    assert_eq!(err("LD V0, 99999999999999999999"), (1, 8, "invalid number `99999999999999999999`".to_string()));
+}
+
+#[test]
+fn columns_count_bytes() {
+    // U+3000 is whitespace three bytes long, the word splitter and expressions agree on it
+    let e = assemble("\u{3000}FOO").unwrap_err();
+    assert_eq!((e.column, e.message.as_str()), (4, "unknown instruction `FOO`"));
+    let e = assemble("DB 1 +\u{3000}nope").unwrap_err();
+    assert_eq!((e.column, e.message.as_str()), (10, "undefined symbol `nope`"));
+}
@@ -0,0 +1,64 @@
+use chip8_core::audio::{SquareWave, WavWriter};
+use std::io::Cursor;
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn square_wave_period_and_duty() {
+    // 1kHz at 8kHz is 8 samples a period, high for the first half
+    let mut wave = SquareWave::new(1000.0, 0.5);
+    let mut out = [0.0; 22];
+    wave.fill(&mut out, 8000, true);
+    let high = [0.5, 0.5, 0.5, 0.5, -0.5, -0.5, -0.5, -0.5];
+    assert_eq!(out[..8], high);
+    assert_eq!(out[8..16], high);
+
+    // The next buffer carries on mid period
+    let mut next = [0.0; 4];
+    wave.fill(&mut next, 8000, true);
+    assert_eq!(next, [-0.5, -0.5, 0.5, 0.5]);
+}
+
+#[test]
+fn square_wave_is_silent_when_off_or_muted() {
+    let mut wave = SquareWave::new(1000.0, 0.5);
+    let mut out = [1.0; 16];
+    wave.fill(&mut out, 8000, false);
+    assert!(out.iter().all(|&s| s == 0.0));
+
+    wave.muted = true;
+    wave.fill(&mut out, 8000, true);
+    assert!(out.iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn wav_header_and_samples() {
+    let mut writer = WavWriter::new(Cursor::new(Vec::new()), 22050).unwrap();
+    writer.write(&[0.0, 1.0, -1.0]).unwrap();
+    writer.write(&[2.0, 0.5]).unwrap();
+    let bytes = writer.finish().unwrap().into_inner();
+
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(u32_at(&bytes, 4), bytes.len() as u32 - 8);
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(&bytes, 16), 16);
+    assert_eq!(u16_at(&bytes, 20), 1, "PCM");
+    assert_eq!(u16_at(&bytes, 22), 1, "mono");
+    assert_eq!(u32_at(&bytes, 24), 22050);
+    assert_eq!(u32_at(&bytes, 28), 22050 * 2, "byte rate");
+    assert_eq!(u16_at(&bytes, 32), 2, "block align");
+    assert_eq!(u16_at(&bytes, 34), 16, "bits per sample");
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(u32_at(&bytes, 40), 5 * 2, "five samples");
+    assert_eq!(bytes.len(), 44 + 10);
+
+    // Samples are clamped and scaled to 16 bits
+    let samples: Vec<i16> = bytes[44..].chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+    assert_eq!(samples, [0, i16::MAX, -i16::MAX, i16::MAX, i16::MAX / 2]);
+}
@@ -0,0 +1,50 @@
+use chip8_core::clock::*;
+use std::time::Duration;
+
+#[test]
+fn frames_follow_real_time_at_any_refresh_rate() {
+    for refresh in [30, 60, 75, 144, 240] {
+        let mut clock = Clock::default();
+        let frames: u32 = (0..refresh).map(|_| clock.advance(Duration::from_secs(1) / refresh)).sum();
+        assert!((59..=60).contains(&frames), "{} frames at {}Hz", frames, refresh);
+    }
+
+    // A long stall only makes up for a few frames
+    let mut clock = Clock::default();
+    assert!(clock.advance(Duration::from_secs(5)) <= 6);
+}
+
+#[test]
+fn instructions_spread_over_frames() {
+    let mut clock = Clock::new(700);
+    let ticks: Vec<usize> = (0..TIMER_HZ).map(|_| clock.frame_ticks()).collect();
+    assert_eq!(ticks.iter().sum::<usize>(), 700);
+    assert!(ticks.iter().all(|&t| t == 11 || t == 12));
+    assert_eq!(clock.ticks_per_frame(), None);
+
+    assert_eq!(Clock::default().ticks_per_frame(), Some(10));
+    assert_eq!(Clock::new(0).ips(), MIN_IPS);
+}
+
+#[test]
+fn speed_pause_and_frame_advance() {
+    let mut clock = Clock::default();
+    assert_eq!(clock.faster(), 1200);
+    assert_eq!(clock.slower(), 600);
+    clock.set_ips(MIN_IPS);
+    assert_eq!(clock.slower(), MIN_IPS);
+
+    clock.paused = true;
+    assert_eq!(clock.advance(Duration::from_secs(1)), 0);
+    clock.frame_advance();
+    assert_eq!(clock.advance(Duration::ZERO), 1);
+    assert_eq!(clock.advance(Duration::from_millis(50)), 0);
+
+    // Time spent paused doesn't come back as a burst of frames
+    clock.paused = false;
+    assert_eq!(clock.advance(Duration::ZERO), 0);
+
+    // Frame advance does nothing while running
+    clock.frame_advance();
+    assert_eq!(clock.advance(Duration::ZERO), 0);
+}
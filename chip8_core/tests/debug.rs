@@ -0,0 +1,126 @@
+use chip8_core::asm::assemble;
+use chip8_core::*;
+
+fn emu(source: &str) -> Emu {
+    let mut emu = Emu::new();
+    emu.load(&assemble(source).unwrap()).unwrap();
+    emu
+}
+
+const COUNTER: &str = "
+    loop:   ADD V0, 1
+            ADD V1, 2
+            JP loop
+";
+
+#[test]
+fn breakpoint_stops_before_the_instruction_and_resumes_past_it() {
+    let mut emu = emu(COUNTER);
+    emu.add_breakpoint(0x202);
+
+    assert_eq!(emu.run(100).unwrap(), Stop::Breakpoint { pc: 0x202 });
+    assert_eq!(emu.registers()[..2], [1, 0]);
+
+    // Resuming runs the instruction under the breakpoint, then stops there on the next pass
+    assert_eq!(emu.run(100).unwrap(), Stop::Breakpoint { pc: 0x202 });
+    assert_eq!(emu.registers()[..2], [2, 2]);
+
+    assert!(emu.remove_breakpoint(0x202));
+    assert_eq!(emu.run(30).unwrap(), Stop::Done);
+}
+
+#[test]
+fn opcode_pattern_breakpoint() {
+    let mut emu = emu(COUNTER);
+    let pattern = OpcodePattern::parse("1NNN").unwrap();
+    emu.add_opcode_breakpoint(pattern);
+
+    assert_eq!(emu.run(100).unwrap(), Stop::Pattern { pc: 0x204, op: 0x1200 });
+    assert_eq!(pattern.to_string(), "1???");
+    assert_eq!(OpcodePattern::parse("8XY4").unwrap().mask, 0xF00F);
+    assert_eq!(OpcodePattern::parse("GXY4"), None);
+}
+
+#[test]
+fn run_until_and_step() {
+    let mut emu = emu(COUNTER);
+    assert_eq!(emu.run_until(0x204, 100).unwrap(), Stop::Reached);
+    assert_eq!(emu.pc(), 0x204);
+
+    emu.step().unwrap();
+    assert_eq!(emu.pc(), 0x200);
+    assert_eq!(emu.run_until(0x300, 10).unwrap(), Stop::Done);
+}
+
+#[test]
+fn state_is_readable_and_writable() {
+    let mut emu = emu("
+                LD I, 0x300
+                LD V5, 7
+                LD DT, V5
+                CALL sub
+        sub:    JP sub
+    ");
+    assert_eq!(emu.run(10).unwrap(), Stop::Done);
+    assert_eq!(emu.i_reg(), 0x300);
+    assert_eq!(emu.registers()[5], 7);
+    assert_eq!(emu.delay_timer(), 7);
+    assert_eq!(emu.stack(), [0x208]);
+
+    emu.set_register(5, 9);
+    emu.set_register(16, 1);
+    emu.set_register(usize::MAX, 1);
+    emu.set_sound_timer(3);
+    emu.write_ram(0x300, &[1, 2]).unwrap();
+    assert_eq!(emu.registers()[5], 9);
+    assert_eq!(emu.registers().len(), 16);
+    assert_eq!(emu.sound_timer(), 3);
+    assert_eq!(emu.ram()[0x300..0x302], [1, 2]);
+    assert!(emu.write_ram(0xFFF, &[1, 2]).is_err());
+}
+
+const SCORE: &str = "
+            LD V0, 42
+            LD I, 0x300
+            LD B, V0
+            LD I, 0x400
+            DRW V0, V0, 2
+    loop:   JP loop
+";
+
+#[test]
+fn write_watchpoint_reports_the_writer() {
+    let mut emu = emu(SCORE);
+    emu.add_watchpoint(0x301..=0x301, Access::Write);
+
+    let stop = emu.run(100).unwrap();
+    assert_eq!(stop, Stop::Watchpoint { pc: 0x204, op: 0xF033, addr: 0x301, access: Access::Write, watch: Access::Write });
+    assert_eq!(emu.pc(), 0x206);
+    assert_eq!(emu.ram()[0x300..0x303], [0, 4, 2]);
+    assert_eq!(stop.to_string(), "write watchpoint on 0x301 by 0xF033 at 0x204");
+}
+
+#[test]
+fn read_and_execute_watchpoints() {
+    let mut emu = emu(SCORE);
+    emu.add_watchpoint(0x400..=0x4FF, Access::Read);
+    emu.add_watchpoint(0x20A..=0x20B, Access::Execute);
+
+    assert_eq!(emu.run(100).unwrap(), Stop::Watchpoint { pc: 0x208, op: 0xD002, addr: 0x400, access: Access::Read, watch: Access::Read });
+    assert_eq!(emu.run(100).unwrap(), Stop::Watchpoint { pc: 0x20A, op: 0x120A, addr: 0x20A, access: Access::Execute, watch: Access::Execute });
+
+    assert!(emu.remove_watchpoint(0x20A..=0x20B, Access::Execute));
+    assert_eq!(emu.run(100).unwrap(), Stop::Done);
+}
+
+#[test]
+fn read_write_watchpoints_report_their_kind() {
+    let mut emu = emu(SCORE);
+    emu.add_watchpoint(0x300..=0x4FF, Access::ReadWrite);
+
+    let stop = emu.run(100).unwrap();
+    assert_eq!(stop, Stop::Watchpoint { pc: 0x204, op: 0xF033, addr: 0x300, access: Access::Write, watch: Access::ReadWrite });
+    assert_eq!(stop.to_string(), "access watchpoint on 0x300, write by 0xF033 at 0x204");
+    assert_eq!(emu.run(100).unwrap(), Stop::Watchpoint { pc: 0x208, op: 0xD002, addr: 0x400, access: Access::Read, watch: Access::ReadWrite });
+    assert!(emu.remove_watchpoint(0x300..=0x4FF, Access::ReadWrite));
+}
@@ -0,0 +1,163 @@
+// Differential tests: run the same ROM and keys on Emu and on the reference interpreter in
+// tests/reference, comparing the whole machine after every instruction. A failure names the
+// first cycle where the two disagree, the instruction that ran and what came out different.
+mod reference;
+
+use chip8_core::asm::assemble;
+use chip8_core::headless::{parse_script, KeyEvent, TICKS_PER_FRAME};
+use chip8_core::*;
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use reference::Reference;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+struct Divergence {
+    cycle: usize,
+    pc: u16,
+    op: u16,
+    what: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cycle {}, {:04X} at {:#05X}: {}", self.cycle, self.op, self.pc, self.what)
+    }
+}
+
+// Run rom for up to cycles instructions, keys are applied by frame the same way the headless
+// runner does. Both sides draw random numbers from the same seeded generator
+fn compare(rom: &[u8], quirks: Quirks, keys: &[KeyEvent], cycles: usize) -> Result<(), Divergence> {
+    let mut emu = Emu::with_quirks(quirks);
+    emu.set_custom_rng(Box::new(ChaCha8Rng::seed_from_u64(0)));
+    emu.load(rom).unwrap();
+    let mut reference = Reference::new(emu.ram(), quirks, Box::new(ChaCha8Rng::seed_from_u64(0)));
+
+    for cycle in 0..cycles {
+        if cycle % TICKS_PER_FRAME == 0 {
+            let frame = (cycle / TICKS_PER_FRAME) as u64;
+            for event in keys.iter().filter(|e| e.frame == frame) {
+                emu.keypress(event.key as usize, event.pressed);
+                reference.keys[event.key as usize] = event.pressed;
+            }
+        }
+
+        let pc = emu.pc();
+        let op = emu.peek_opcode(pc).unwrap_or(0);
+        let diverged = |what: String| Divergence { cycle, pc, op, what };
+        match (emu.tick(), reference.step()) {
+            (Ok(()), Ok(())) => (),
+            (Err(_), Err(_)) => return Ok(()),
+            (Ok(()), Err(e)) => return Err(diverged(format!("only the reference failed: {}", e))),
+            (Err(e), Ok(())) => return Err(diverged(format!("only Emu failed: {}", e))),
+        }
+        if let Some(what) = difference(&emu, &reference) {
+            return Err(diverged(what));
+        }
+
+        if (cycle + 1) % TICKS_PER_FRAME == 0 {
+            emu.tick_timers();
+            reference.tick_timers();
+        }
+    }
+    Ok(())
+}
+
+// First part of the machine that differs, Emu's value first
+fn difference(emu: &Emu, reference: &Reference) -> Option<String> {
+    if emu.pc() != reference.pc {
+        return Some(format!("pc is {:#05X}, reference has {:#05X}", emu.pc(), reference.pc));
+    }
+    if let Some(x) = (0..16).find(|&x| emu.registers()[x] != reference.v[x]) {
+        return Some(format!("V{:X} is {:#04X}, reference has {:#04X}", x, emu.registers()[x], reference.v[x]));
+    }
+    if emu.i_reg() != reference.i {
+        return Some(format!("I is {:#05X}, reference has {:#05X}", emu.i_reg(), reference.i));
+    }
+    if emu.stack() != reference.stack {
+        return Some(format!("stack is {:03X?}, reference has {:03X?}", emu.stack(), reference.stack));
+    }
+    if (emu.delay_timer(), emu.sound_timer()) != (reference.dt, reference.st) {
+        return Some(format!("timers are {} / {}, reference has {} / {}",
+            emu.delay_timer(), emu.sound_timer(), reference.dt, reference.st));
+    }
+    if emu.ram() != reference.ram {
+        let addr = (0..reference.ram.len()).find(|&a| emu.ram()[a] != reference.ram[a])?;
+        return Some(format!("RAM at {:#05X} is {:#04X}, reference has {:#04X}", addr, emu.ram()[addr], reference.ram[addr]));
+    }
+    if emu.get_display() != reference.screen {
+        let index = (0..reference.screen.len()).find(|&p| emu.get_display()[p] != reference.screen[p])?;
+        let (x, y) = (index % reference::WIDTH, index / reference::WIDTH);
+        return Some(format!("pixel ({}, {}) is {}, reference has {}", x, y, emu.get_display()[index], reference.screen[index]));
+    }
+    None
+}
+
+const PRESETS: [Quirks; 4] = [Quirks::vip(), Quirks::chip48(), Quirks::schip(), Quirks::xochip()];
+
+#[test]
+fn test_roms_match_the_reference() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    for name in ["opcodes", "flags", "quirks", "keypad"] {
+        let rom = fs::read(dir.join(name).with_extension("ch8")).unwrap();
+        let keys = fs::read_to_string(dir.join(name).with_extension("keys"))
+            .map(|text| parse_script(&text).unwrap())
+            .unwrap_or_default();
+        for quirks in PRESETS {
+            if let Err(divergence) = compare(&rom, quirks, &keys, 2000) {
+                panic!("{} with {:?}: {}", name, quirks, divergence);
+            }
+        }
+    }
+}
+
+#[test]
+fn divergence_names_the_cycle_and_opcode() {
+    // 8XY6 with the shift quirk on one side only, the third instruction is the first to differ
+    let rom = assemble("LD V0, 0x10\nLD V1, 0x04\nSHR V0, V1\nJP 0x206").unwrap();
+    let mut emu = Emu::with_quirks(Quirks::schip());
+    emu.load(&rom).unwrap();
+    let mut reference = Reference::new(emu.ram(), Quirks::vip(), Box::new(ChaCha8Rng::seed_from_u64(0)));
+    for _ in 0..3 {
+        emu.tick().unwrap();
+        reference.step().unwrap();
+    }
+    assert_eq!(difference(&emu, &reference).unwrap(), "V0 is 0x08, reference has 0x02");
+
+    let divergence = Divergence { cycle: 2, pc: 0x204, op: 0x8016, what: "V0 is 0x08, reference has 0x02".to_string() };
+    assert_eq!(divergence.to_string(), "cycle 2, 8016 at 0x204: V0 is 0x08, reference has 0x02");
+}
+
+// Programs made of 8XYN and DXYN with a few loads and skips mixed in, where flag bugs hide
+fn instruction() -> impl Strategy<Value = u16> {
+    let reg = 0..16u16;
+    prop_oneof![
+        (reg.clone(), any::<u8>()).prop_map(|(x, nn)| 0x6000 | x << 8 | nn as u16),
+        (reg.clone(), any::<u8>()).prop_map(|(x, nn)| 0x7000 | x << 8 | nn as u16),
+        (reg.clone(), reg.clone(), prop::sample::select(vec![0, 1, 2, 3, 4, 5, 6, 7, 0xE]))
+            .prop_map(|(x, y, n)| 0x8000 | x << 8 | y << 4 | n),
+        (reg.clone(), reg.clone(), 0..16u16).prop_map(|(x, y, n)| 0xD000 | x << 8 | y << 4 | n),
+        (0..0x100u16).prop_map(|addr| 0xA000 | addr),
+        (reg.clone(), any::<u8>()).prop_map(|(x, nn)| 0x3000 | x << 8 | nn as u16),
+        (reg.clone(), reg.clone()).prop_map(|(x, y)| 0x9000 | x << 8 | y << 4),
+        (reg.clone(), any::<u8>()).prop_map(|(x, nn)| 0xC000 | x << 8 | nn as u16),
+        reg.clone().prop_map(|x| 0xF033 | x << 8),
+        reg.prop_map(|x| 0xF01E | x << 8),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_programs_match_the_reference(
+        ops in prop::collection::vec(instruction(), 1..200),
+        preset in 0..PRESETS.len(),
+    ) {
+        let rom: Vec<u8> = ops.iter().flat_map(|op| op.to_be_bytes()).collect();
+        if let Err(divergence) = compare(&rom, PRESETS[preset], &[], ops.len()) {
+            return Err(TestCaseError::fail(divergence.to_string()));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use chip8_core::disasm::{disassemble, format_instruction, Syntax};
+use chip8_core::*;
+
+fn rom(program: &[u16]) -> Vec<u8> {
+    program.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
+
+// The listing's text, one line each
+fn text(rom: &[u8], mode: Mode, syntax: Syntax) -> Vec<String> {
+    disassemble(rom, START_ADDR, mode, syntax).iter().map(|line| line.to_string()).collect()
+}
+
+// A spread of opcodes with their Cowgod and Octo text
+const SAMPLES: &[(u16, &str, &str)] = &[
+    (0x00E0, "CLS", "clear"),
+    (0x00EE, "RET", "return"),
+    (0x1234, "JP 0x234", "jump 0x234"),
+    (0x2ABC, "CALL 0xABC", ":call 0xABC"),
+    (0x3A2A, "SE VA, 0x2A", "if va != 0x2A then"),
+    (0x4B07, "SNE VB, 0x07", "if vb == 0x07 then"),
+    (0x5120, "SE V1, V2", "if v1 != v2 then"),
+    (0x6F00, "LD VF, 0x00", "vf := 0x00"),
+    (0x7C01, "ADD VC, 0x01", "vc += 0x01"),
+    (0x8AB0, "LD VA, VB", "va := vb"),
+    (0x8AB4, "ADD VA, VB", "va += vb"),
+    (0x8AB5, "SUB VA, VB", "va -= vb"),
+    (0x8AB7, "SUBN VA, VB", "va =- vb"),
+    (0x8ABE, "SHL VA, VB", "va <<= vb"),
+    (0x9120, "SNE V1, V2", "if v1 == v2 then"),
+    (0xA2F0, "LD I, 0x2F0", "i := 0x2F0"),
+    (0xB300, "JP V0, 0x300", "jump0 0x300"),
+    (0xC30F, "RND V3, 0x0F", "v3 := random 0x0F"),
+    (0xD125, "DRW V1, V2, 5", "sprite v1 v2 5"),
+    (0xE49E, "SKP V4", "if v4 -key then"),
+    (0xE4A1, "SKNP V4", "if v4 key then"),
+    (0xF507, "LD V5, DT", "v5 := delay"),
+    (0xF50A, "LD V5, K", "v5 := key"),
+    (0xF518, "LD ST, V5", "buzzer := v5"),
+    (0xF529, "LD F, V5", "i := hex v5"),
+    (0xF533, "LD B, V5", "bcd v5"),
+    (0xF555, "LD [I], V5", "save v5"),
+    (0xF565, "LD V5, [I]", "load v5"),
+    (0x00C4, "SCD 4", "scroll-down 4"),
+    (0x00FB, "SCR", "scroll-right"),
+    (0x00FF, "HIGH", "hires"),
+    (0xD010, "DRW V0, V1, 0", "sprite v0 v1 0"),
+    (0xF630, "LD HF, V6", "i := bighex v6"),
+    (0xF775, "LD R, V7", "saveflags v7"),
+    (0x00D2, "SCU 2", "scroll-up 2"),
+    (0x5232, "SAVE V2, V3", "save v2 - v3"),
+    (0x5323, "LOAD V3, V2", "load v3 - v2"),
+    (0xF201, "PLANE 2", "plane 2"),
+    (0xF002, "AUDIO", "audio"),
+    (0xF83A, "PITCH V8", "pitch := v8"),
+];
+
+#[test]
+fn instructions_in_both_syntaxes() {
+    let ops: Vec<u16> = SAMPLES.iter().map(|&(op, _, _)| op).collect();
+    let cowgod = text(&rom(&ops), Mode::XoChip, Syntax::Cowgod);
+    let octo = text(&rom(&ops), Mode::XoChip, Syntax::Octo);
+    for (i, &(op, want_cowgod, want_octo)) in SAMPLES.iter().enumerate() {
+        assert_eq!(cowgod[i], want_cowgod, "{:04X}", op);
+        assert_eq!(octo[i], want_octo, "{:04X}", op);
+        assert_eq!(format_instruction(decode(op).unwrap(), 0, Syntax::Cowgod), want_cowgod);
+    }
+}
+
+#[test]
+fn lines_carry_their_address_bytes_and_instruction() {
+    let listing = disassemble(&rom(&[0x6012, 0xD125]), 0x300, Mode::Chip8, Syntax::Cowgod);
+    assert_eq!(listing.len(), 2);
+    assert_eq!((listing[1].addr, listing[1].bytes.as_slice()), (0x302, &[0xD1, 0x25][..]));
+    assert_eq!(listing[1].instruction, Some(Instruction::Draw { x: 1, y: 2, n: 5 }));
+    assert_eq!(listing[1].mnemonic, "DRW");
+    assert_eq!(listing[1].operands, ["V1", "V2", "5"]);
+}
+
+#[test]
+fn undecodable_words_are_data() {
+    // Unassigned, SYS, and an instruction from a later platform than the mode
+    let program = rom(&[0x5121, 0x0123, 0x00FF]);
+    assert_eq!(text(&program, Mode::Chip8, Syntax::Cowgod), ["DW 0x5121", "DW 0x0123", "DW 0x00FF"]);
+    assert_eq!(text(&program, Mode::Chip8, Syntax::Octo), ["0x51 0x21", "0x01 0x23", "0x00 0xFF"]);
+    assert_eq!(text(&program, Mode::SuperChip, Syntax::Cowgod)[2], "HIGH");
+
+    let listing = disassemble(&program, START_ADDR, Mode::Chip8, Syntax::Cowgod);
+    assert!(listing.iter().all(|line| line.instruction.is_none()));
+}
+
+#[test]
+fn an_odd_last_byte_is_data() {
+    let program = [0x00, 0xE0, 0xAB];
+    assert_eq!(text(&program, Mode::Chip8, Syntax::Cowgod), ["CLS", "DB 0xAB"]);
+    assert_eq!(text(&program, Mode::Chip8, Syntax::Octo), ["clear", "0xAB"]);
+
+    let listing = disassemble(&program, START_ADDR, Mode::Chip8, Syntax::Cowgod);
+    assert_eq!((listing[1].addr, listing[1].bytes.as_slice()), (0x202, &[0xAB][..]));
+}
+
+#[test]
+fn long_i_takes_4_bytes_on_xo_chip() {
+    let program = rom(&[0xF000, 0xBEEF, 0x00E0]);
+    let listing = disassemble(&program, START_ADDR, Mode::XoChip, Syntax::Cowgod);
+    assert_eq!(listing.len(), 2);
+    assert_eq!(listing[0].bytes, [0xF0, 0x00, 0xBE, 0xEF]);
+    assert_eq!(listing[0].to_string(), "LD I, LONG 0xBEEF");
+    assert_eq!((listing[1].addr, listing[1].to_string()), (0x204, "CLS".to_string()));
+    assert_eq!(text(&program, Mode::XoChip, Syntax::Octo), ["i := long 0xBEEF", "clear"]);
+
+    // Not an instruction before XO-CHIP, and data when the address is cut off
+    assert_eq!(text(&program, Mode::SuperChip, Syntax::Cowgod), ["DW 0xF000", "JP V0, 0xEEF", "CLS"]);
+    assert_eq!(text(&rom(&[0xF000]), Mode::XoChip, Syntax::Cowgod), ["DW 0xF000"]);
+}
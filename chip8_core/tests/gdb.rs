@@ -0,0 +1,175 @@
+use chip8_core::asm::assemble;
+use chip8_core::gdb::{GdbStatus, GdbStub};
+use chip8_core::*;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// Minimal scripted RSP client
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn send(&mut self, body: &str) {
+        self.send_bytes(body.as_bytes());
+    }
+
+    fn send_bytes(&mut self, body: &[u8]) {
+        let sum = body.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+        self.stream.write_all(b"$").unwrap();
+        self.stream.write_all(body).unwrap();
+        write!(self.stream, "#{:02x}", sum).unwrap();
+    }
+
+    // Next packet body, skipping acks
+    fn recv(&mut self) -> String {
+        let mut packet = Vec::new();
+        let mut byte = [0];
+        loop {
+            self.stream.read_exact(&mut byte).unwrap();
+            match byte[0] {
+                b'+' if packet.is_empty() => continue,
+                b'#' => break,
+                b => packet.push(b),
+            }
+        }
+        let mut sum = [0; 2];
+        self.stream.read_exact(&mut sum).unwrap();
+        let body = String::from_utf8(packet[1..].to_vec()).unwrap();
+        let expected = body.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+        assert_eq!(std::str::from_utf8(&sum).unwrap(), format!("{:02x}", expected));
+        body
+    }
+
+    fn ask(&mut self, body: &str) -> String {
+        self.send(body);
+        self.recv()
+    }
+}
+
+// Serve rom until the client detaches, handing back the machine
+fn serve(rom: &str) -> (Client, thread::JoinHandle<Emu>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let rom = assemble(rom).unwrap();
+
+    let server = thread::spawn(move || {
+        let mut emu = Emu::new();
+        emu.load(&rom).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut stub = GdbStub::new(stream);
+        while stub.run_frame(&mut emu, 10).unwrap() != GdbStatus::Detached {
+            thread::yield_now();
+        }
+        emu
+    });
+
+    let client = Client { stream: TcpStream::connect(("127.0.0.1", port)).unwrap() };
+    client.stream.set_nodelay(true).unwrap();
+    (client, server)
+}
+
+#[test]
+fn scripted_session() {
+    let (mut client, server) = serve("
+                LD V0, 5
+                LD I, 0x300
+        loop:   ADD V1, 1
+                LD [I], V1
+                JP loop
+    ");
+    assert!(client.ask("qSupported:xmlRegisters=i386").contains("qXfer:features:read+"));
+    assert!(client.ask("qXfer:features:read:target.xml:0,ffff").starts_with("l<?xml"));
+    assert_eq!(client.ask("?"), "S05");
+
+    // Fresh machine: everything zero, PC at 0x200 little endian
+    assert_eq!(client.ask("g"), format!("{}00000002000000", "00".repeat(16)));
+
+    // Single step twice, then read V0 and I
+    assert_eq!(client.ask("s"), "S05");
+    assert_eq!(client.ask("s"), "S05");
+    assert_eq!(client.ask("p0"), "05");
+    assert_eq!(client.ask("p10"), "0003");
+    assert_eq!(client.ask("p11"), "0402");
+
+    // Break at the store and continue into it
+    assert_eq!(client.ask("Z0,206,2"), "OK");
+    client.send("c");
+    assert_eq!(client.recv(), "S05");
+    assert_eq!(client.ask("p11"), "0602");
+    assert_eq!(client.ask("p1"), "01");
+
+    // Memory write, then read it back
+    assert_eq!(client.ask("M300,2:abcd"), "OK");
+    assert_eq!(client.ask("m300,2"), "abcd");
+
+    // Write watchpoint replaces the breakpoint
+    assert_eq!(client.ask("z0,206,2"), "OK");
+    assert_eq!(client.ask("Z2,300,1"), "OK");
+    client.send("c");
+    assert_eq!(client.recv(), "T05watch:300;");
+    assert_eq!(client.ask("m300,2"), "0501");   // The store we were stopped on, V0 and V1
+
+    // Register writes
+    assert_eq!(client.ask("P3=7f"), "OK");
+    assert_eq!(client.ask("p3"), "7f");
+    assert_eq!(client.ask("P12=01"), "E01");    // SP is read only
+    assert_eq!(client.ask("vMustReplyEmpty"), "");
+
+    assert_eq!(client.ask("D"), "OK");
+    let emu = server.join().unwrap();
+    assert_eq!(emu.registers()[3], 0x7F);
+}
+
+#[test]
+fn bad_packets_get_errors() {
+    let (mut client, server) = serve("loop: JP loop");
+
+    // A command byte that isn't ASCII, and one that isn't even UTF-8
+    client.send("\u{e9}1");
+    assert_eq!(client.recv(), "E01");
+    client.send_bytes(&[0xFF, b'0']);
+    assert_eq!(client.recv(), "E01");
+
+    // Ranges that would overflow
+    assert_eq!(client.ask(&format!("m{:x},2", usize::MAX)), "E0e");
+    assert_eq!(client.ask(&format!("m1,{:x}", usize::MAX)), "E0e");
+    assert_eq!(client.ask("m1,ffffffffffffffffffff"), "E01");
+    assert_eq!(client.ask("Z2,300,10000"), "E01");
+
+    // Resume addresses past the top of memory
+    assert_eq!(client.ask("c10000"), "E01");
+    assert_eq!(client.ask("s10200"), "E01");
+    assert_eq!(client.ask("p11"), "0002");
+    assert!(client.ask(&format!("qXfer:features:read:target.xml:10,{:x}", usize::MAX)).starts_with('l'));
+
+    // Still talking
+    assert_eq!(client.ask("m200,2"), "1200");
+    assert_eq!(client.ask("D"), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn access_watchpoints_stop_on_reads_and_writes() {
+    let (mut client, server) = serve("
+                LD I, 0x300
+                LD V0, [I]
+                LD [I], V0
+        loop:   JP loop
+    ");
+    assert_eq!(client.ask("Z4,300,1"), "OK");
+    client.send("c");
+    assert_eq!(client.recv(), "T05awatch:300;");
+    client.send("c");
+    assert_eq!(client.recv(), "T05awatch:300;");
+    assert_eq!(client.ask("p11"), "0602");
+
+    assert_eq!(client.ask("z4,300,1"), "OK");
+    assert_eq!(client.ask("D"), "OK");
+    let emu = server.join().unwrap();
+    assert!(emu.watchpoints().is_empty());
+}
@@ -0,0 +1,112 @@
+// Golden tests: run the test ROMs in tests/roms headlessly, read the ROM's own verdict for
+// each check off the screen and compare it with the answer written down here, then compare the
+// whole screen with the PBM image stored next to the ROM.
+//
+// The ROMs are written for this suite in the in-tree assembler rather than copied from the
+// community test suites, so they can live in the repo without licensing questions. They cover
+// the same ground: opcodes, flags, quirks and the keypad. Each check draws a tick or a cross,
+// see tests/roms/check.asm. The assembled .ch8 files are committed too and checked against
+// their source, so the ROMs can be run in other interpreters as they are.
+//
+// The verdicts are what keeps the goldens honest: UPDATE_GOLDEN only writes a screen whose
+// ticks and crosses are the expected ones. After an intended change, rebuild the ROMs and
+// goldens with
+//
+//     UPDATE_GOLDEN=1 cargo test --test golden
+use chip8_core::asm::assemble_file;
+use chip8_core::headless::{ascii, parse_script, Runner};
+use chip8_core::screenshot::Screenshot;
+use chip8_core::*;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const FRAMES: u64 = 120;
+
+// Sprites from tests/roms/check.asm, drawn 5 pixels into each 16 pixel wide cell
+const TICK: [u8; 5] = [0b00000001, 0b00000010, 0b10000100, 0b01001000, 0b00110000];
+const CROSS: [u8; 5] = [0b10001000, 0b01010000, 0b00100000, 0b01010000, 0b10001000];
+
+fn roms() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms")
+}
+
+// Ticks and crosses in the result grid, in check order up to the first empty cell
+fn verdicts(emu: &Emu) -> Vec<bool> {
+    let sprite = |cell: usize| -> [u8; 5] {
+        let (x, y) = (cell % 4 * 16 + 5, cell / 4 * 6);
+        std::array::from_fn(|row| {
+            (0..8).fold(0, |byte, col| byte << 1 | (emu.get_display()[x + col + (y + row) * emu.display_width()] != 0) as u8)
+        })
+    };
+    (0..16)
+        .map(sprite)
+        .map_while(|cell| match cell {
+            TICK => Some(true),
+            CROSS => Some(false),
+            _ => None,
+        })
+        .collect()
+}
+
+// Run rom under quirks with the key script rom.keys if there is one, checking the ROM's
+// verdicts and then the screen against golden.pbm
+fn check_golden(rom: &str, quirks: Quirks, expected: &[bool], golden: &str) {
+    let dir = roms();
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+
+    let assembled = assemble_file(&dir.join(rom).with_extension("asm")).unwrap();
+    let rom_path = dir.join(rom).with_extension("ch8");
+    if update {
+        fs::write(&rom_path, &assembled).unwrap();
+    }
+    assert_eq!(fs::read(&rom_path).unwrap(), assembled, "{} is out of date with its source", rom_path.display());
+
+    let script = match fs::read_to_string(dir.join(rom).with_extension("keys")) {
+        Ok(text) => parse_script(&text).unwrap(),
+        Err(_) => Vec::new(),
+    };
+    let mut emu = Emu::with_quirks(quirks);
+    emu.set_seed(0);
+    emu.load(&assembled).unwrap();
+    let outcome = Runner { frames: FRAMES, script, ..Runner::default() }.run(&mut emu);
+    assert!(outcome.passed(), "{}: {}", rom, outcome);
+    assert_eq!(verdicts(&emu), expected, "{} with {:?}, screen was\n{}", rom, quirks, ascii(&emu));
+
+    let screen = Screenshot::pbm().encode(&emu);
+    let golden_path = dir.join(golden).with_extension("pbm");
+    if update {
+        fs::write(&golden_path, &screen).unwrap();
+    }
+    let expected = fs::read(&golden_path).unwrap();
+    assert!(screen == expected, "{} differs from {}, screen was\n{}", rom, golden_path.display(), ascii(&emu));
+}
+
+#[test]
+fn opcodes() {
+    // Every check passes whatever the quirks
+    for quirks in [Quirks::vip(), Quirks::chip48(), Quirks::schip(), Quirks::xochip()] {
+        check_golden("opcodes", quirks, &[true; 16], "opcodes");
+    }
+}
+
+#[test]
+fn flags() {
+    for quirks in [Quirks::vip(), Quirks::chip48(), Quirks::schip(), Quirks::xochip()] {
+        check_golden("flags", quirks, &[true; 16], "flags");
+    }
+}
+
+#[test]
+fn quirks() {
+    // A tick for each quirk that's on, in the order vf_reset, memory, shift, jump, clip
+    check_golden("quirks", Quirks::vip(), &[true, true, false, false, true], "quirks-vip");
+    check_golden("quirks", Quirks::chip48(), &[false, false, true, true, true], "quirks-chip48");
+    check_golden("quirks", Quirks::schip(), &[false, false, true, true, true], "quirks-schip");
+    check_golden("quirks", Quirks::xochip(), &[false, true, false, false, false], "quirks-xochip");
+}
+
+#[test]
+fn keypad() {
+    check_golden("keypad", Quirks::vip(), &[true; 5], "keypad");
+}
@@ -0,0 +1,63 @@
+use chip8_core::asm::assemble;
+use chip8_core::headless::{ascii, parse_script, Condition, Outcome, Runner};
+use chip8_core::*;
+
+fn emu(source: &str) -> Emu {
+    let mut emu = Emu::new();
+    emu.set_seed(0);
+    emu.load(&assemble(source).unwrap()).unwrap();
+    emu
+}
+
+// Waits for a key, then jumps to pass if it was 5 and to fail otherwise
+const KEY_TEST: &str = "
+    LD V0, K
+    SE V0, 5
+    JP fail
+pass:
+    JP pass
+fail:
+    JP fail
+";
+
+#[test]
+fn scripted_keys_decide_the_outcome() {
+    let runner = Runner {
+        script: parse_script("# press 5\n3 5 down\n6 5 up\n").unwrap(),
+        pass: vec![Condition::parse("pc=206").unwrap()],
+        fail: vec![Condition::parse("pc=208").unwrap()],
+        ..Runner::default()
+    };
+    let outcome = runner.run(&mut emu(KEY_TEST));
+    assert_eq!(outcome, Outcome::Passed { frame: 3, condition: Some(Condition::Pc(0x206)) });
+
+    let wrong_key = Runner { script: parse_script("3 6 down").unwrap(), ..runner.clone() };
+    assert!(matches!(wrong_key.run(&mut emu(KEY_TEST)), Outcome::Failed { frame: 3, .. }));
+
+    let no_keys = Runner { script: Vec::new(), frames: 20, ..runner };
+    assert_eq!(no_keys.run(&mut emu(KEY_TEST)), Outcome::TimedOut { frames: 20 });
+}
+
+#[test]
+fn errors_and_bad_scripts_are_reported() {
+    let outcome = Runner::default().run(&mut emu("RET"));
+    assert_eq!(outcome, Outcome::Error { frame: 0, error: EmuError::StackUnderflow });
+
+    let err = parse_script("1 5 down\n2 G up").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert_eq!(Condition::parse("vA=3F"), Some(Condition::Register { x: 0xA, value: 0x3F }));
+    assert_eq!(Condition::parse("mem[0x300]=1"), Some(Condition::Memory { addr: 0x300, value: 1 }));
+    assert_eq!(Condition::parse("vG=1"), None);
+}
+
+#[test]
+fn ascii_dump() {
+    let mut emu = emu("LD I, 0\nDRW V0, V0, 5\nhere: JP here");
+    assert!(Runner { frames: 1, ..Runner::default() }.run(&mut emu).passed());
+
+    let text = ascii(&emu);
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows.len(), SCREEN_HEIGHT);
+    assert_eq!(&rows[0][..6], "####..");     // Top of the 0 glyph
+    assert_eq!(&rows[1][..6], "#..#..");
+}
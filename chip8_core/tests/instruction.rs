@@ -0,0 +1,70 @@
+use chip8_core::*;
+
+#[test]
+fn every_opcode_round_trips() {
+    for op in 0..=u16::MAX {
+        if let Ok(instr) = decode(op) {
+            assert_eq!(encode(instr), op, "{:#06X} decoded to {:?}", op, instr);
+        }
+    }
+}
+
+#[test]
+fn built_instructions_encode_and_decode_back() {
+    use Instruction::*;
+    let instrs = [
+        Cls,
+        Hires,
+        ScrollDown { n: 0xF },
+        Call { nnn: 0xFFF },
+        SkipEqImm { x: 0xF, nn: 0xFF },
+        LoadRange { x: 0xE, y: 0x1 },
+        Shl { x: 0x3, y: 0xC },
+        Draw { x: 0, y: 0xF, n: 0 },
+        SkipNotKey { x: 0x9 },
+        LoadILong,
+        Plane { n: 0xF },
+        LoadFlags { x: 0x7 },
+    ];
+    for instr in instrs {
+        assert_eq!(decode(encode(instr)), Ok(instr), "{:?} encoded to {:#06X}", instr, encode(instr));
+    }
+}
+
+#[test]
+fn out_of_range_operands_are_masked() {
+    // Only the low bits of each field make it into the opcode, so these come back changed
+    assert_eq!(encode(Instruction::Add { x: 0x1A, y: 0xFB }), 0x8AB4);
+    assert_eq!(decode(encode(Instruction::Add { x: 0x1A, y: 0xFB })), Ok(Instruction::Add { x: 0xA, y: 0xB }));
+    assert_eq!(decode(encode(Instruction::Jump { nnn: 0xF234 })), Ok(Instruction::Jump { nnn: 0x234 }));
+    assert_eq!(decode(encode(Instruction::Draw { x: 1, y: 2, n: 0x15 })), Ok(Instruction::Draw { x: 1, y: 2, n: 5 }));
+
+    // A SYS address that lands on another 00NN opcode decodes as that one
+    assert_eq!(decode(encode(Instruction::Sys { nnn: 0x0E0 })), Ok(Instruction::Cls));
+}
+
+#[test]
+fn decodes_known_opcodes() {
+    assert_eq!(decode(0x00E0), Ok(Instruction::Cls));
+    assert_eq!(decode(0x1234), Ok(Instruction::Jump { nnn: 0x234 }));
+    assert_eq!(decode(0x8AB4), Ok(Instruction::Add { x: 0xA, y: 0xB }));
+    assert_eq!(decode(0xD125), Ok(Instruction::Draw { x: 1, y: 2, n: 5 }));
+    assert_eq!(decode(0xF30A), Ok(Instruction::WaitKey { x: 3 }));
+    assert_eq!(decode(0xF201), Ok(Instruction::Plane { n: 2 }));
+    assert_eq!(decode(0x0123), Ok(Instruction::Sys { nnn: 0x123 }));
+}
+
+#[test]
+fn rejects_unassigned_opcodes() {
+    for op in [0x5121, 0x8008, 0x9001, 0xE000, 0xF0FF, 0xF103] {
+        assert_eq!(decode(op), Err(DecodeError { op }));
+    }
+}
+
+#[test]
+fn instructions_know_their_platform() {
+    assert_eq!(decode(0x00FF).unwrap().mode(), Mode::SuperChip);
+    assert_eq!(decode(0xF000).unwrap().mode(), Mode::XoChip);
+    assert_eq!(decode(0xF000).unwrap().size(), 4);
+    assert_eq!(decode(0x6A2A).unwrap().mode(), Mode::Chip8);
+}
@@ -0,0 +1,64 @@
+use chip8_core::asm::assemble;
+use chip8_core::headless::{parse_script, Runner};
+use chip8_core::movie::Movie;
+use chip8_core::*;
+
+// Every key press draws a random digit at the next spot along
+const ROM: &str = "
+loop:
+    LD V0, K
+    RND V1, 0x0F
+    LD F, V1
+    DRW V2, V3, 5
+    ADD V2, 5
+wait:
+    SKNP V0
+    JP wait
+    JP loop
+";
+
+fn record(rom: &[u8], seed: u64) -> (Movie, Emu) {
+    let mut emu = Emu::with_quirks(Quirks::vip());
+    emu.set_seed(seed);
+    emu.load(rom).unwrap();
+    let mut movie = Movie::new(rom, &emu, 10);
+    let script = parse_script("2 1 down\n4 1 up\n4 1 up\n6 2 down\n9 2 up\n12 3 down\n").unwrap();
+    for event in &script {
+        movie.record_key(event.frame, event.key, event.pressed);
+    }
+    let runner = Runner { frames: 20, script, ..Runner::default() };
+    assert!(runner.run(&mut emu).passed());
+    movie.end(20, &emu);
+    (movie, emu)
+}
+
+#[test]
+fn replay_reproduces_the_recording() {
+    let rom = assemble(ROM).unwrap();
+    let (movie, recorded) = record(&rom, 42);
+    assert_eq!(movie.events.len(), 5);      // The repeated release was dropped
+
+    let movie = Movie::parse(&movie.to_string()).unwrap();
+    let mut replay = movie.setup(&rom).unwrap();
+    assert!(movie.runner().run(&mut replay).passed());
+    assert_eq!(replay.get_display(), recorded.get_display());
+    assert_eq!(movie.screen_matches(&replay), Some(true));
+
+    // Another seed draws other digits
+    let (_, other) = record(&rom, 7);
+    assert_eq!(movie.screen_matches(&other), Some(false));
+}
+
+#[test]
+fn replay_checks_the_rom_and_file() {
+    let rom = assemble(ROM).unwrap();
+    let (movie, _) = record(&rom, 1);
+    assert!(matches!(movie.setup(&rom[1..]), Err(MovieError::RomMismatch { .. })));
+
+    let text = movie.to_string();
+    assert!(text.starts_with("chip8-movie 1\nrom "));
+    assert!(text.contains("\nquirks vf_reset memory clip\nrng chacha8 1\nticks 10\nframes 20\n"));
+    assert!(Movie::parse(&text.replace("chip8-movie 1", "chip8-movie 9")).is_err());
+    let err = Movie::parse(&format!("{}7 Z down\n", text)).unwrap_err();
+    assert_eq!(err, MovieError::Parse { line: 14, message: "bad key: Z (expected 0 - F)".to_string() });
+}
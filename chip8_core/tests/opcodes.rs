@@ -0,0 +1,548 @@
+// One or more tests per opcode handler, each running a few raw instructions
+use chip8_core::*;
+
+// Quirks that make no difference to a test
+const VIP: Quirks = Quirks::vip();
+
+fn emu(quirks: Quirks, program: &[u16]) -> Emu {
+    let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut emu = Emu::with_quirks(quirks);
+    emu.set_seed(0);
+    emu.load(&rom).unwrap();
+    emu
+}
+
+// Run the first n instructions of program
+fn run(quirks: Quirks, program: &[u16], n: usize) -> Emu {
+    let mut emu = emu(quirks, program);
+    for _ in 0..n {
+        emu.tick().unwrap();
+    }
+    emu
+}
+
+fn pixel(emu: &Emu, x: usize, y: usize) -> u8 {
+    emu.get_display()[x + y * emu.display_width()]
+}
+
+fn lit(emu: &Emu) -> usize {
+    emu.get_display().iter().filter(|&&px| px != 0).count()
+}
+
+#[test]
+fn cls_00e0() {
+    // Draw the 0 glyph, then clear it
+    let mut emu = run(VIP, &[0xA000, 0xD005, 0x00E0], 2);
+    assert_ne!(lit(&emu), 0);
+    emu.tick().unwrap();
+    assert_eq!(lit(&emu), 0);
+}
+
+#[test]
+fn jump_1nnn() {
+    let emu = run(VIP, &[0x1208], 1);
+    assert_eq!(emu.pc(), 0x208);
+}
+
+#[test]
+fn call_2nnn_and_ret_00ee() {
+    let program = [0x2204, 0x1202, 0x00EE];
+    let emu = run(VIP, &program, 1);
+    assert_eq!(emu.pc(), 0x204);
+    assert_eq!(emu.stack(), [0x202]);
+
+    let emu = run(VIP, &program, 2);
+    assert_eq!(emu.pc(), 0x202);
+    assert!(emu.stack().is_empty());
+}
+
+#[test]
+fn skips_3xnn_4xnn_5xy0_9xy0() {
+    // V0 = 5, V1 = 5, V2 = 6, then the skip under test
+    let setup = [0x6005, 0x6105, 0x6206];
+    let cases = [
+        (0x3005, 0x20A),    // SE V0, 5
+        (0x3006, 0x208),    // SE V0, 6
+        (0x4006, 0x20A),    // SNE V0, 6
+        (0x4005, 0x208),    // SNE V0, 5
+        (0x5010, 0x20A),    // SE V0, V1
+        (0x5020, 0x208),    // SE V0, V2
+        (0x9020, 0x20A),    // SNE V0, V2
+        (0x9010, 0x208),    // SNE V0, V1
+    ];
+    for (op, pc) in cases {
+        let emu = run(VIP, &[setup[0], setup[1], setup[2], op], 4);
+        assert_eq!(emu.pc(), pc, "{:04X}", op);
+    }
+}
+
+#[test]
+fn load_6xnn_and_add_7xnn_wrap_without_touching_vf() {
+    let emu = run(VIP, &[0x6F07, 0x63FF, 0x7302], 3);
+    assert_eq!(emu.registers()[0x3], 0x01);
+    assert_eq!(emu.registers()[0xF], 0x07);
+}
+
+#[test]
+fn move_8xy0() {
+    let emu = run(VIP, &[0x6142, 0x8010], 2);
+    assert_eq!(emu.registers()[..2], [0x42, 0x42]);
+}
+
+#[test]
+fn logic_8xy1_8xy2_8xy3_and_vf_reset() {
+    for (op, result) in [(0x8011, 0xFC), (0x8012, 0x0C), (0x8013, 0xF0)] {
+        // V0 = 0x3C, V1 = 0xCC, VF = 9
+        let program = [0x603C, 0x61CC, 0x6F09, op];
+        let emu = run(VIP, &program, 4);
+        assert_eq!(emu.registers()[0], result, "{:04X}", op);
+        assert_eq!(emu.registers()[0xF], 0, "{:04X} resets VF on the VIP", op);
+
+        let emu = run(Quirks::schip(), &program, 4);
+        assert_eq!(emu.registers()[0], result, "{:04X}", op);
+        assert_eq!(emu.registers()[0xF], 9, "{:04X} leaves VF alone without vf_reset", op);
+    }
+}
+
+#[test]
+fn add_8xy4_carry() {
+    let cases = [
+        (0xFF, 0x01, 0x00, 1),
+        (0xFE, 0x01, 0xFF, 0),
+        (0x80, 0x80, 0x00, 1),
+        (0xFF, 0xFF, 0xFE, 1),
+        (0x00, 0x00, 0x00, 0),
+    ];
+    for (a, b, sum, carry) in cases {
+        let emu = run(VIP, &[0x6000 | a, 0x6100 | b, 0x8014], 3);
+        assert_eq!(emu.registers()[0], sum as u8, "{:02X} + {:02X}", a, b);
+        assert_eq!(emu.registers()[0xF], carry, "{:02X} + {:02X}", a, b);
+    }
+
+    // When VF is the destination the flag overwrites the sum
+    let emu = run(VIP, &[0x6FFF, 0x6102, 0x8F14], 3);
+    assert_eq!(emu.registers()[0xF], 1);
+    let emu = run(VIP, &[0x6F01, 0x6102, 0x8F14], 3);
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn sub_8xy5_borrow() {
+    // VF is 1 when there's no borrow, including when the two are equal
+    let cases = [
+        (0x05, 0x03, 0x02, 1),
+        (0x03, 0x05, 0xFE, 0),
+        (0x05, 0x05, 0x00, 1),
+        (0x00, 0xFF, 0x01, 0),
+    ];
+    for (a, b, difference, flag) in cases {
+        let emu = run(VIP, &[0x6000 | a, 0x6100 | b, 0x8015], 3);
+        assert_eq!(emu.registers()[0], difference as u8, "{:02X} - {:02X}", a, b);
+        assert_eq!(emu.registers()[0xF], flag, "{:02X} - {:02X}", a, b);
+    }
+
+    let emu = run(VIP, &[0x6F03, 0x6105, 0x8F15], 3);
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn subn_8xy7_borrow() {
+    let cases = [
+        (0x03, 0x05, 0x02, 1),
+        (0x05, 0x03, 0xFE, 0),
+        (0x05, 0x05, 0x00, 1),
+    ];
+    for (a, b, difference, flag) in cases {
+        let emu = run(VIP, &[0x6000 | a, 0x6100 | b, 0x8017], 3);
+        assert_eq!(emu.registers()[0], difference as u8, "{:02X} - {:02X}", b, a);
+        assert_eq!(emu.registers()[0xF], flag, "{:02X} - {:02X}", b, a);
+    }
+
+    let emu = run(VIP, &[0x6F05, 0x6103, 0x8F17], 3);
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn shr_8xy6_shifted_out_bit() {
+    // VIP shifts VY into VX
+    let emu = run(VIP, &[0x6000, 0x6105, 0x8016], 3);
+    assert_eq!(emu.registers()[..2], [0x02, 0x05]);
+    assert_eq!(emu.registers()[0xF], 1);
+    let emu = run(VIP, &[0x6000, 0x6104, 0x8016], 3);
+    assert_eq!(emu.registers()[0], 0x02);
+    assert_eq!(emu.registers()[0xF], 0);
+
+    // With the shift quirk VX shifts in place and VY is ignored
+    let emu = run(Quirks::schip(), &[0x6081, 0x6104, 0x8016], 3);
+    assert_eq!(emu.registers()[0], 0x40);
+    assert_eq!(emu.registers()[0xF], 1);
+
+    // VF as the destination ends up holding the flag
+    let emu = run(VIP, &[0x6103, 0x8F16], 2);
+    assert_eq!(emu.registers()[0xF], 1);
+}
+
+#[test]
+fn shl_8xye_shifted_out_bit() {
+    let emu = run(VIP, &[0x6000, 0x6181, 0x801E], 3);
+    assert_eq!(emu.registers()[..2], [0x02, 0x81]);
+    assert_eq!(emu.registers()[0xF], 1);
+    let emu = run(VIP, &[0x6000, 0x6141, 0x801E], 3);
+    assert_eq!(emu.registers()[0], 0x82);
+    assert_eq!(emu.registers()[0xF], 0);
+
+    let emu = run(Quirks::schip(), &[0x6081, 0x6140, 0x801E], 3);
+    assert_eq!(emu.registers()[0], 0x02);
+    assert_eq!(emu.registers()[0xF], 1);
+
+    let emu = run(VIP, &[0x6140, 0x8F1E], 2);
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn load_i_annn() {
+    let emu = run(VIP, &[0xA123], 1);
+    assert_eq!(emu.i_reg(), 0x123);
+}
+
+#[test]
+fn jump_offset_bnnn() {
+    // V0 = 4, V3 = 8
+    let program = [0x6004, 0x6308, 0xB300];
+    assert_eq!(run(VIP, &program, 3).pc(), 0x304);
+    assert_eq!(run(Quirks::schip(), &program, 3).pc(), 0x308, "BXNN adds VX");
+}
+
+#[test]
+fn random_cxnn_is_masked() {
+    for mask in [0x00, 0x0F, 0xA5] {
+        let emu = run(VIP, &[0xC000 | mask], 1);
+        assert_eq!(emu.registers()[0] & !(mask as u8), 0);
+    }
+}
+
+#[test]
+fn draw_dxyn_sets_vf_on_collision() {
+    // Glyph 0 at (0, 0), then again, then on an empty spot
+    let program = [0xA000, 0xD015, 0xD015, 0x6108, 0xD105];
+    let emu = run(VIP, &program, 2);
+    assert_eq!(emu.registers()[0xF], 0);
+    assert_eq!(lit(&emu), 14);
+    assert_eq!(pixel(&emu, 0, 0), 1);
+    assert_eq!(pixel(&emu, 1, 1), 0);
+
+    // Drawing over it again erases it and reports the collision
+    let emu = run(VIP, &program, 3);
+    assert_eq!(emu.registers()[0xF], 1);
+    assert_eq!(lit(&emu), 0);
+
+    // VF goes back to 0 on a draw that doesn't collide
+    let emu = run(VIP, &program, 5);
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn draw_dxyn_wraps_the_start_and_clips_or_wraps_the_rest() {
+    // I = 0x20A, a full 8x1 line, drawn at (60, 31)
+    let program = [0xA20A, 0x603C, 0x611F, 0xD011, 0x1208, 0xFF00];
+    let clipped = run(VIP, &program, 4);
+    assert_eq!(lit(&clipped), 4);
+    assert_eq!(pixel(&clipped, 63, 31), 1);
+    assert_eq!(pixel(&clipped, 0, 31), 0);
+
+    let wrapped = run(Quirks::xochip(), &program, 4);
+    assert_eq!(lit(&wrapped), 8);
+    assert_eq!(pixel(&wrapped, 0, 31), 1);
+    assert_eq!(pixel(&wrapped, 3, 31), 1);
+
+    // A start past the edge wraps whatever the quirks, (68, 33) draws at (4, 1)
+    let program = [0xA20A, 0x6044, 0x6121, 0xD011, 0x1208, 0xFF00];
+    let emu = run(VIP, &program, 4);
+    assert_eq!(lit(&emu), 8);
+    assert_eq!(pixel(&emu, 4, 1), 1);
+    assert_eq!(pixel(&emu, 11, 1), 1);
+}
+
+#[test]
+fn skip_key_ex9e_and_exa1() {
+    // V0 = 7, then the skip under test
+    for (op, pressed, pc) in [(0xE09E, true, 0x206), (0xE09E, false, 0x204), (0xE0A1, false, 0x206), (0xE0A1, true, 0x204)] {
+        let mut emu = emu(VIP, &[0x6007, op]);
+        emu.keypress(7, pressed);
+        emu.tick().unwrap();
+        emu.tick().unwrap();
+        assert_eq!(emu.pc(), pc, "{:04X} with key {}", op, if pressed { "down" } else { "up" });
+    }
+}
+
+#[test]
+fn wait_key_fx0a_blocks_until_a_key_is_down() {
+    let mut emu = emu(VIP, &[0x6333, 0xF30A, 0x1204]);
+    for _ in 0..20 {
+        emu.tick().unwrap();
+    }
+    assert_eq!(emu.pc(), 0x202, "still waiting");
+    assert_eq!(emu.registers()[3], 0x33, "VX untouched while waiting");
+
+    // Timers keep running while it waits
+    emu.set_delay_timer(2);
+    emu.tick_timers();
+    assert_eq!(emu.delay_timer(), 1);
+
+    emu.keypress(0xB, true);
+    emu.tick().unwrap();
+    assert_eq!(emu.pc(), 0x204);
+    assert_eq!(emu.registers()[3], 0xB);
+}
+
+#[test]
+fn wait_key_fx0a_at_the_top_of_memory() {
+    // Fetch wraps pc to 0 past the last instruction, waiting has to step back over that
+    let mut emu = Emu::with_quirks(Quirks::xochip());
+    emu.set_mode(Mode::XoChip);
+    emu.load(&[]).unwrap();
+    emu.write_ram(0xFFFE, &[0xF3, 0x0A]).unwrap();
+    emu.set_pc(0xFFFE);
+    for _ in 0..3 {
+        emu.tick().unwrap();
+    }
+    assert_eq!(emu.pc(), 0xFFFE);
+
+    emu.keypress(5, true);
+    emu.tick().unwrap();
+    assert_eq!(emu.pc(), 0);
+    assert_eq!(emu.registers()[3], 5);
+}
+
+#[test]
+fn keypress_past_key_f_is_ignored() {
+    let mut emu = emu(VIP, &[0xF30A]);
+    emu.keypress(16, true);
+    emu.keypress(usize::MAX, true);
+    emu.tick().unwrap();
+    assert_eq!(emu.pc(), 0x200, "no key is down");
+}
+
+#[test]
+fn timers_fx07_fx15_fx18() {
+    let mut emu = emu(VIP, &[0x6005, 0xF015, 0xF018, 0xF107]);
+    for _ in 0..3 {
+        emu.tick().unwrap();
+    }
+    assert_eq!(emu.delay_timer(), 5);
+    assert_eq!(emu.sound_timer(), 5);
+    assert!(emu.is_beeping());
+
+    emu.tick_timers();
+    emu.tick().unwrap();
+    assert_eq!(emu.registers()[1], 4);
+    assert_eq!(emu.sound_timer(), 4);
+}
+
+#[test]
+fn add_i_fx1e() {
+    let emu = run(VIP, &[0xA2FF, 0x6003, 0xF01E], 3);
+    assert_eq!(emu.i_reg(), 0x302);
+    assert_eq!(emu.registers()[0xF], 0, "VF isn't an overflow flag here");
+}
+
+#[test]
+fn font_fx29_points_at_the_glyph() {
+    let emu = run(VIP, &[0x600A, 0xF029], 2);
+    let glyph = emu.i_reg() as usize;
+    assert_eq!(emu.ram()[glyph..glyph + 5], [0xF0, 0x90, 0xF0, 0x90, 0x90]);
+}
+
+#[test]
+fn bcd_fx33() {
+    let emu = run(VIP, &[0x60FE, 0xA300, 0xF033], 3);
+    assert_eq!(emu.ram()[0x300..0x303], [2, 5, 4]);
+    assert_eq!(emu.i_reg(), 0x300);
+}
+
+#[test]
+fn store_fx55_and_load_fx65() {
+    // V0 - V2 = 1, 2, 3 stored at 0x300, then read back into fresh registers
+    let program = [0x6001, 0x6102, 0x6203, 0xA300, 0xF255, 0xA300, 0x6000, 0x6100, 0x6200, 0xF165];
+    let emu = run(VIP, &program, 5);
+    assert_eq!(emu.ram()[0x300..0x304], [1, 2, 3, 0]);
+    assert_eq!(emu.i_reg(), 0x303, "memory quirk moves I past the last register");
+
+    let emu = run(VIP, &program, 10);
+    assert_eq!(emu.registers()[..3], [1, 2, 0], "only V0 - V1 come back");
+    assert_eq!(emu.i_reg(), 0x302);
+
+    let emu = run(Quirks::schip(), &program, 10);
+    assert_eq!(emu.registers()[..3], [1, 2, 0]);
+    assert_eq!(emu.i_reg(), 0x300, "I stays put without the memory quirk");
+}
+
+// Like emu, for the opcodes only SUPER-CHIP or XO-CHIP know
+fn emu_in(mode: Mode, program: &[u16]) -> Emu {
+    let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut emu = Emu::with_quirks(mode.quirks());
+    emu.set_mode(mode);
+    emu.load(&rom).unwrap();
+    emu
+}
+
+fn run_in(mode: Mode, program: &[u16], n: usize) -> Emu {
+    let mut emu = emu_in(mode, program);
+    for _ in 0..n {
+        emu.tick().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn scroll_down_00cn() {
+    // The 0 glyph's top row moves from row 0 to row 3
+    let mut chip8 = emu(VIP, &[0x00C3]);
+    assert!(chip8.tick().is_err(), "CHIP-8 has no scrolling");
+
+    let emu = run_in(Mode::SuperChip, &[0x00FF, 0xA000, 0xD005, 0x00C3], 4);
+    assert_eq!(pixel(&emu, 0, 3), 1);
+    assert_eq!((0..3).map(|y| pixel(&emu, 0, y)).sum::<u8>(), 0);
+    assert_eq!(lit(&emu), 14, "nothing falls off the bottom");
+}
+
+#[test]
+fn scroll_right_00fb_and_left_00fc() {
+    let program = [0x00FF, 0xA000, 0xD005, 0x00FB, 0x00FC];
+    let emu = run_in(Mode::SuperChip, &program, 4);
+    assert_eq!((0..4).map(|x| pixel(&emu, x, 0)).sum::<u8>(), 0);
+    assert_eq!((4..8).map(|x| pixel(&emu, x, 0)).sum::<u8>(), 4);
+
+    let emu = run_in(Mode::SuperChip, &program, 5);
+    assert_eq!((0..4).map(|x| pixel(&emu, x, 0)).sum::<u8>(), 4);
+    assert_eq!((4..8).map(|x| pixel(&emu, x, 0)).sum::<u8>(), 0);
+}
+
+#[test]
+fn lores_00fe_and_hires_00ff() {
+    let program = [0xA000, 0xD005, 0x00FF, 0xD005, 0x00FE];
+    let emu = run_in(Mode::SuperChip, &program, 2);
+    assert_eq!((emu.display_width(), emu.display_height()), (64, 32));
+
+    let emu = run_in(Mode::SuperChip, &program, 3);
+    assert_eq!((emu.display_width(), emu.display_height()), (128, 64));
+    assert_eq!(lit(&emu), 0, "switching resolution clears the screen");
+
+    let emu = run_in(Mode::SuperChip, &program, 5);
+    assert_eq!((emu.display_width(), emu.display_height()), (64, 32));
+    assert_eq!(lit(&emu), 0);
+}
+
+#[test]
+fn draw_dxy0_is_a_16x16_sprite() {
+    let mut emu = emu_in(Mode::SuperChip, &[0x00FF, 0xA300, 0xD000, 0xD000]);
+    emu.write_ram(0x300, &[0xFF; 32]).unwrap();
+    for _ in 0..3 {
+        emu.tick().unwrap();
+    }
+    assert_eq!(lit(&emu), 256);
+    assert_eq!(pixel(&emu, 15, 15), 1);
+    assert_eq!(pixel(&emu, 16, 0), 0);
+    assert_eq!(emu.registers()[0xF], 0);
+
+    emu.tick().unwrap();
+    assert_eq!(lit(&emu), 0);
+    assert_eq!(emu.registers()[0xF], 1);
+
+    let emu = run(VIP, &[0xA000, 0xD000], 2);
+    assert_eq!(lit(&emu), 0, "DXY0 draws nothing on CHIP-8");
+}
+
+#[test]
+fn big_font_fx30_points_at_the_digit() {
+    let zero = run_in(Mode::SuperChip, &[0x6000, 0xF030], 2).i_reg();
+    let emu = run_in(Mode::SuperChip, &[0x6017, 0xF030], 2);
+    assert_eq!(emu.i_reg(), zero + 70, "digits are 10 bytes, only the low nibble counts");
+
+    let small = run_in(Mode::SuperChip, &[0x6007, 0xF029], 2).i_reg() as usize;
+    let big = emu.i_reg() as usize;
+    assert_ne!(big, small);
+    assert!(emu.ram()[big..big + 10].iter().any(|&row| row != 0));
+}
+
+#[test]
+fn flags_fx75_and_fx85() {
+    // V0 - V2 = 1, 2, 3 go out to the flags, the registers get cleared, then V0 - V1 come back
+    let program = [0x6001, 0x6102, 0x6203, 0xF275, 0x6000, 0x6100, 0x6200, 0xF185];
+    let emu = run_in(Mode::SuperChip, &program, 8);
+    assert_eq!(emu.registers()[..3], [1, 2, 0]);
+}
+
+#[test]
+fn save_5xy2_and_load_5xy3_ranges() {
+    // V1 - V3 = 1, 2, 3 saved at 0x300 both ways around, I stays put
+    let program = [0x6101, 0x6202, 0x6303, 0xA300, 0x5132, 0xA310, 0x5312];
+    let emu = run_in(Mode::XoChip, &program, 7);
+    assert_eq!(emu.ram()[0x300..0x304], [1, 2, 3, 0]);
+    assert_eq!(emu.ram()[0x310..0x314], [3, 2, 1, 0]);
+    assert_eq!(emu.i_reg(), 0x310);
+
+    let program = [0xA300, 0x5243, 0xA300, 0x5653];
+    let mut emu = emu_in(Mode::XoChip, &program);
+    emu.write_ram(0x300, &[7, 8, 9]).unwrap();
+    for _ in 0..2 {
+        emu.tick().unwrap();
+    }
+    assert_eq!(emu.registers()[1..6], [0, 7, 8, 9, 0]);
+    assert_eq!(emu.i_reg(), 0x300);
+
+    emu.tick().unwrap();
+    emu.tick().unwrap();
+    assert_eq!(emu.registers()[4..7], [9, 8, 7], "loading in reverse fills V6 first");
+}
+
+#[test]
+fn long_i_f000_nnnn() {
+    let emu = run_in(Mode::XoChip, &[0xF000, 0xBEEF], 1);
+    assert_eq!(emu.i_reg(), 0xBEEF);
+    assert_eq!(emu.pc(), 0x204);
+
+    // Skips step over all 4 bytes
+    let emu = run_in(Mode::XoChip, &[0x3000, 0xF000, 0xBEEF, 0x6001], 2);
+    assert_eq!(emu.i_reg(), 0);
+    assert_eq!(emu.registers()[0], 1);
+}
+
+#[test]
+fn planes_fn01() {
+    // The 0 glyph on plane 2 only, then a CLS with plane 1 selected leaves it
+    let program = [0xF201, 0xA000, 0xD005, 0xF101, 0x00E0, 0xF201, 0x00E0];
+    let emu = run_in(Mode::XoChip, &program, 3);
+    assert_eq!(pixel(&emu, 0, 0), 2);
+
+    let emu = run_in(Mode::XoChip, &program, 5);
+    assert_eq!(pixel(&emu, 0, 0), 2);
+    assert_eq!(lit(&emu), 14);
+
+    let emu = run_in(Mode::XoChip, &program, 7);
+    assert_eq!(lit(&emu), 0);
+
+    // Both planes take a sprite each, the second right after the first
+    let emu = run_in(Mode::XoChip, &[0xF301, 0xA000, 0xD005], 3);
+    let glyphs = &emu.ram()[0..10];
+    let row = |y: usize| (0..8).map(|x| pixel(&emu, x, y)).collect::<Vec<_>>();
+    let expect = |y: usize| (0..8).map(|x| (glyphs[y] >> (7 - x) & 1) | (glyphs[y + 5] >> (7 - x) & 1) << 1).collect::<Vec<_>>();
+    for y in 0..5 {
+        assert_eq!(row(y), expect(y));
+    }
+}
+
+#[test]
+fn audio_f002_and_pitch_fx3a() {
+    let pattern: Vec<u8> = (0..16).collect();
+    let mut emu = emu_in(Mode::XoChip, &[0xA300, 0xF002, 0x6070, 0xF03A]);
+    emu.write_ram(0x300, &pattern).unwrap();
+    emu.tick().unwrap();
+    emu.tick().unwrap();
+    assert_eq!(emu.audio_pattern(), &pattern[..]);
+    assert_eq!(emu.audio_rate(), 4000.0, "pitch 64 plays at 4000Hz");
+
+    emu.tick().unwrap();
+    emu.tick().unwrap();
+    assert_eq!(emu.audio_rate(), 8000.0, "48 steps up is an octave");
+}
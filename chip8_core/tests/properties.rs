@@ -0,0 +1,98 @@
+// Property tests: arbitrary ROMs, keys and machine setups must never panic the core, and the
+// machine has to stay consistent for as long as it runs. fuzz/ runs the same checks under
+// cargo-fuzz for longer sessions.
+use chip8_core::*;
+use proptest::prelude::*;
+
+const MAX_TICKS: usize = 2000;
+const TICKS_PER_FRAME: usize = 10;
+
+#[derive(Debug, Clone)]
+struct Input {
+    mode: Mode,
+    quirks: Quirks,
+    seed: u64,
+    keys: Vec<(usize, u8, bool)>,   // Tick, key, pressed
+    rom: Vec<u8>,
+}
+
+fn mode() -> impl Strategy<Value = Mode> {
+    prop_oneof![Just(Mode::Chip8), Just(Mode::SuperChip), Just(Mode::XoChip)]
+}
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 5]>().prop_map(|[vf_reset, memory, shift, jump, clip]| Quirks { vf_reset, memory, shift, jump, clip })
+}
+
+fn input() -> impl Strategy<Value = Input> {
+    let keys = prop::collection::vec((0..MAX_TICKS, 0..16u8, any::<bool>()), 0..32);
+    let rom = prop::collection::vec(any::<u8>(), 0..1024);
+    (mode(), quirks(), any::<u64>(), keys, rom)
+        .prop_map(|(mode, quirks, seed, keys, rom)| Input { mode, quirks, seed, keys, rom })
+}
+
+// Run input until it errors, exits or runs out of ticks, checking the machine after every
+// instruction. Writes to the font are allowed, but only through an instruction that stores
+// there, which a watchpoint picks up
+fn check(input: &Input) -> Result<(), TestCaseError> {
+    let mut emu = Emu::with_quirks(input.quirks);
+    emu.set_mode(input.mode);
+    emu.set_seed(input.seed);
+    let font = emu.ram()[..FONT_END as usize].to_vec();
+    if emu.load(&input.rom).is_err() {
+        prop_assert!(START_ADDR as usize + input.rom.len() > emu.ram().len());
+        return Ok(());
+    }
+    emu.add_watchpoint(0..=FONT_END - 1, Access::Write);
+    let mut font_written = false;
+
+    for tick in 0..MAX_TICKS {
+        for &(_, key, pressed) in input.keys.iter().filter(|(t, ..)| *t == tick) {
+            emu.keypress(key as usize, pressed);
+        }
+        let pc = emu.pc() as usize;
+        match emu.run(1) {
+            Ok(Stop::Watchpoint { .. }) => font_written = true,
+            Ok(Stop::Exited) => break,
+            Ok(_) => (),
+            Err(_) => break,
+        }
+        prop_assert!(pc + 2 <= emu.ram().len(), "ran an instruction from outside RAM at {:#X}", pc);
+        prop_assert!(emu.stack().len() <= STACK_SIZE);
+        if !font_written {
+            prop_assert_eq!(&emu.ram()[..FONT_END as usize], &font[..], "font changed without a store");
+        }
+        if (tick + 1) % TICKS_PER_FRAME == 0 {
+            emu.tick_timers();
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_roms_keep_the_machine_consistent(input in input()) {
+        check(&input)?;
+    }
+
+    // Random bytes mostly stop at the first unknown opcode, valid instructions get further
+    #[test]
+    fn arbitrary_instructions_keep_the_machine_consistent(
+        mode in mode(),
+        quirks in quirks(),
+        seed in any::<u64>(),
+        ops in prop::collection::vec(any::<u16>().prop_filter("decodes", |op| decode(*op).is_ok()), 0..256),
+    ) {
+        let rom = ops.iter().flat_map(|op| op.to_be_bytes()).collect();
+        check(&Input { mode, quirks, seed, keys: Vec::new(), rom })?;
+    }
+
+    #[test]
+    fn oversized_roms_are_refused(extra in 1..64usize, mode in mode()) {
+        let mut emu = Emu::new();
+        emu.set_mode(mode);
+        let rom = vec![0; emu.ram().len() - START_ADDR as usize + extra];
+        let refused = matches!(emu.load(&rom), Err(EmuError::RomTooLarge { .. }));
+        prop_assert!(refused);
+    }
+}
@@ -0,0 +1,199 @@
+// Reference interpreter for differential tests: plain CHIP-8 written as directly as possible,
+// one match on the opcode's nibbles and no shared code with Emu. It has to agree with Emu
+// on every instruction, so keep it simple enough to check by eye against the spec.
+use chip8_core::Quirks;
+use rand::{Rng, RngCore};
+
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+pub struct Reference {
+    pub ram: Vec<u8>,
+    pub screen: Vec<u8>,        // 1 for a lit pixel, row by row
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub dt: u8,
+    pub st: u8,
+    pub keys: [bool; 16],
+    quirks: Quirks,
+    rng: Box<dyn RngCore>,
+}
+
+impl Reference {
+    // Starts from a memory image with the font and ROM already in place
+    pub fn new(ram: &[u8], quirks: Quirks, rng: Box<dyn RngCore>) -> Self {
+        Self {
+            ram: ram.to_vec(),
+            screen: vec![0; WIDTH * HEIGHT],
+            v: [0; 16],
+            i: 0,
+            pc: 0x200,
+            stack: Vec::new(),
+            dt: 0,
+            st: 0,
+            keys: [false; 16],
+            quirks,
+            rng,
+        }
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
+    }
+
+    // Errors say why the instruction couldn't run
+    pub fn step(&mut self) -> Result<(), String> {
+        let pc = self.pc as usize;
+        if pc + 1 >= self.ram.len() {
+            return Err(format!("fetch past the end of RAM at {:#X}", pc));
+        }
+        let op = u16::from_be_bytes([self.ram[pc], self.ram[pc + 1]]);
+        self.pc = self.pc.wrapping_add(2);
+
+        let x = ((op >> 8) & 0xF) as usize;
+        let y = ((op >> 4) & 0xF) as usize;
+        let n = (op & 0xF) as usize;
+        let nn = (op & 0xFF) as u8;
+        let nnn = op & 0xFFF;
+        let unknown = || Err(format!("unknown opcode {:04X}", op));
+
+        match (op >> 12, x, y, n) {
+            (0x0, 0, 0, 0) => (),
+            (0x0, 0, 0xE, 0) => self.screen.fill(0),
+            (0x0, 0, 0xE, 0xE) => self.pc = self.stack.pop().ok_or("stack underflow")?,
+            (0x1, ..) => self.pc = nnn,
+            (0x2, ..) => {
+                if self.stack.len() == 16 {
+                    return Err("stack overflow".to_string());
+                }
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            },
+            (0x3, ..) => self.skip_if(self.v[x] == nn),
+            (0x4, ..) => self.skip_if(self.v[x] != nn),
+            (0x5, _, _, 0) => self.skip_if(self.v[x] == self.v[y]),
+            (0x6, ..) => self.v[x] = nn,
+            (0x7, ..) => self.v[x] = self.v[x].wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.v[x] = self.v[y],
+            (0x8, _, _, 0x1..=0x3) => {
+                self.v[x] = match n {
+                    0x1 => self.v[x] | self.v[y],
+                    0x2 => self.v[x] & self.v[y],
+                    _ => self.v[x] ^ self.v[y],
+                };
+                if self.quirks.vf_reset {
+                    self.v[0xF] = 0;
+                }
+            },
+            // Flags are written last, so VF as the destination ends up holding the flag
+            (0x8, _, _, 0x4) => {
+                let sum = self.v[x] as u16 + self.v[y] as u16;
+                self.v[x] = sum as u8;
+                self.v[0xF] = (sum > 0xFF) as u8;
+            },
+            (0x8, _, _, 0x5) => {
+                let (a, b) = (self.v[x], self.v[y]);
+                self.v[x] = a.wrapping_sub(b);
+                self.v[0xF] = (a >= b) as u8;
+            },
+            (0x8, _, _, 0x7) => {
+                let (a, b) = (self.v[x], self.v[y]);
+                self.v[x] = b.wrapping_sub(a);
+                self.v[0xF] = (b >= a) as u8;
+            },
+            (0x8, _, _, 0x6) => {
+                let value = if self.quirks.shift { self.v[x] } else { self.v[y] };
+                self.v[x] = value >> 1;
+                self.v[0xF] = value & 1;
+            },
+            (0x8, _, _, 0xE) => {
+                let value = if self.quirks.shift { self.v[x] } else { self.v[y] };
+                self.v[x] = value << 1;
+                self.v[0xF] = value >> 7;
+            },
+            (0x9, _, _, 0) => self.skip_if(self.v[x] != self.v[y]),
+            (0xA, ..) => self.i = nnn,
+            (0xB, ..) => {
+                let offset = if self.quirks.jump { self.v[x] } else { self.v[0] };
+                self.pc = nnn + offset as u16;
+            },
+            (0xC, ..) => self.v[x] = self.rng.gen::<u8>() & nn,
+            (0xD, ..) => self.draw(self.v[x] as usize, self.v[y] as usize, n)?,
+            (0xE, _, 0x9, 0xE) => self.skip_if(self.key(self.v[x])?),
+            (0xE, _, 0xA, 0x1) => self.skip_if(!self.key(self.v[x])?),
+            (0xF, _, 0x0, 0x7) => self.v[x] = self.dt,
+            (0xF, _, 0x0, 0xA) => match self.keys.iter().position(|&down| down) {
+                Some(key) => self.v[x] = key as u8,
+                None => self.pc -= 2,
+            },
+            (0xF, _, 0x1, 0x5) => self.dt = self.v[x],
+            (0xF, _, 0x1, 0x8) => self.st = self.v[x],
+            (0xF, _, 0x1, 0xE) => self.i = self.i.wrapping_add(self.v[x] as u16),
+            (0xF, _, 0x2, 0x9) => self.i = self.v[x] as u16 * 5,
+            (0xF, _, 0x3, 0x3) => {
+                let at = self.ram_range(3)?;
+                self.ram[at].copy_from_slice(&[self.v[x] / 100, self.v[x] / 10 % 10, self.v[x] % 10]);
+            },
+            (0xF, _, 0x5, 0x5) => {
+                let at = self.ram_range(x + 1)?;
+                self.ram[at].copy_from_slice(&self.v[..=x]);
+                if self.quirks.memory {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            },
+            (0xF, _, 0x6, 0x5) => {
+                let at = self.ram_range(x + 1)?;
+                self.v[..=x].copy_from_slice(&self.ram[at]);
+                if self.quirks.memory {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            },
+            _ => return unknown(),
+        }
+        Ok(())
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    fn key(&self, vx: u8) -> Result<bool, String> {
+        self.keys.get(vx as usize).copied().ok_or_else(|| format!("invalid key {:#04X}", vx))
+    }
+
+    fn ram_range(&self, len: usize) -> Result<std::ops::Range<usize>, String> {
+        let start = self.i as usize;
+        if start + len > self.ram.len() {
+            return Err(format!("memory access out of bounds at {:#X}", start));
+        }
+        Ok(start..start + len)
+    }
+
+    fn draw(&mut self, x: usize, y: usize, rows: usize) -> Result<(), String> {
+        let sprite = self.ram[self.ram_range(rows)?].to_vec();
+        let (x, y) = (x % WIDTH, y % HEIGHT);
+        self.v[0xF] = 0;
+        for (row, byte) in sprite.into_iter().enumerate() {
+            for col in 0..8 {
+                if byte & (0x80 >> col) == 0 {
+                    continue;
+                }
+                let (px, py) = (x + col, y + row);
+                if self.quirks.clip && (px >= WIDTH || py >= HEIGHT) {
+                    continue;
+                }
+                let pixel = &mut self.screen[px % WIDTH + py % HEIGHT * WIDTH];
+                if *pixel == 1 {
+                    self.v[0xF] = 1;
+                }
+                *pixel ^= 1;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use chip8_core::rewind::Rewind;
+
+// Push states in order, then check popping gives them back newest first
+fn round_trip(states: &[Vec<u8>]) -> Rewind {
+    let mut history = Rewind::new(usize::MAX);
+    for state in states {
+        history.push(state.clone());
+    }
+    for state in states[..states.len() - 1].iter().rev() {
+        assert_eq!(history.pop(), Some(state.as_slice()));
+    }
+    assert_eq!(history.pop(), None);
+    history
+}
+
+#[test]
+fn identical_states_cost_a_few_bytes() {
+    let state = vec![0x5A; 5000];
+    let mut history = Rewind::new(usize::MAX);
+    for _ in 0..10 {
+        history.push(state.clone());
+    }
+    assert_eq!(history.len(), 9);
+    assert!(history.used() <= 9 * 5, "{} bytes", history.used());
+    round_trip(&vec![state; 10]);
+}
+
+#[test]
+fn fully_different_states() {
+    round_trip(&[vec![0x00; 300], vec![0xFF; 300], (0..300).map(|i| i as u8).collect()]);
+}
+
+#[test]
+fn runs_past_the_varint_boundaries() {
+    // Unchanged and changed runs of 127, 128, 16383 and 16384 bytes, the edges of one, two and
+    // three byte varints
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    for len in [127, 128, 16383, 16384] {
+        before.extend(std::iter::repeat_n(1, len));
+        after.extend(std::iter::repeat_n(1, len));
+        before.extend(std::iter::repeat_n(2, len));
+        after.extend(std::iter::repeat_n(3, len));
+    }
+    round_trip(&[before.clone(), after.clone(), before]);
+}
+
+#[test]
+fn states_that_change_size() {
+    // A mode switch resizes RAM
+    round_trip(&[vec![7; 4096], vec![7; 65536], vec![8; 4096]]);
+}
+
+#[test]
+fn oldest_frames_go_once_over_budget() {
+    let budget = 1000;
+    let mut history = Rewind::new(budget);
+    let states: Vec<Vec<u8>> = (0..50u8).map(|n| vec![n; 100]).collect();
+    for state in &states {
+        history.push(state.clone());
+        assert!(history.used() <= budget);
+    }
+    // Each delta is 100 changed bytes plus a tag and two varints, 9 fit
+    assert_eq!(history.len(), 9);
+    for state in states[40..49].iter().rev() {
+        assert_eq!(history.pop(), Some(state.as_slice()));
+    }
+    assert_eq!(history.pop(), None);
+    assert_eq!(history.used(), 0);
+}
+
+#[test]
+fn clear_forgets_everything() {
+    let mut history = Rewind::new(usize::MAX);
+    history.push(vec![1; 10]);
+    history.push(vec![2; 10]);
+    history.clear();
+    assert!(history.is_empty());
+    assert_eq!(history.pop(), None);
+
+    // The next push starts a fresh history
+    history.push(vec![3; 10]);
+    assert!(history.is_empty());
+}
@@ -0,0 +1,132 @@
+use chip8_core::*;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// CXFF into V0 - V7, so the registers hold the first 8 random bytes
+const PROGRAM: [u16; 8] = [0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF, 0xC4FF, 0xC5FF, 0xC6FF, 0xC7FF];
+
+fn emu(algorithm: RngAlgorithm, seed: u64) -> Emu {
+    let rom: Vec<u8> = PROGRAM.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut emu = Emu::with_quirks(Quirks::vip());
+    emu.set_rng_algorithm(algorithm, seed);
+    emu.load(&rom).unwrap();
+    emu
+}
+
+// The next n random bytes
+fn bytes(emu: &mut Emu, n: usize) -> Vec<u8> {
+    for _ in 0..n {
+        emu.tick().unwrap();
+    }
+    let start = (emu.pc() - START_ADDR) as usize / 2 - n;
+    emu.registers()[start..start + n].to_vec()
+}
+
+// Counts up, one byte at a time
+struct Counter(u32);
+
+impl RngCore for Counter {
+    fn next_u32(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.iter_mut().for_each(|b| *b = self.next_u32() as u8);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[test]
+fn same_seed_same_sequence() {
+    for algorithm in [RngAlgorithm::ChaCha8, RngAlgorithm::Xorshift] {
+        let first = bytes(&mut emu(algorithm, 42), 8);
+        assert_eq!(bytes(&mut emu(algorithm, 42), 8), first, "{:?}", algorithm);
+        assert_ne!(bytes(&mut emu(algorithm, 43), 8), first, "{:?}", algorithm);
+    }
+    assert_ne!(bytes(&mut emu(RngAlgorithm::ChaCha8, 42), 8), bytes(&mut emu(RngAlgorithm::Xorshift, 42), 8));
+}
+
+#[test]
+fn chacha8_is_rand_chacha_seeded_from_u64() {
+    let mut rng = ChaCha8Rng::seed_from_u64(1234);
+    let expected: Vec<u8> = (0..8).map(|_| rng.gen()).collect();
+    assert_eq!(bytes(&mut emu(RngAlgorithm::ChaCha8, 1234), 8), expected);
+}
+
+#[test]
+fn xorshift_is_xorshift64_star() {
+    // One round of splitmix64 turns the seed into the state
+    let mut z = 1234u64.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    let mut state = z ^ (z >> 31);
+
+    // A byte is the low byte of the high half of the output
+    let expected: Vec<u8> = (0..8).map(|_| {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u8
+    }).collect();
+    assert_eq!(bytes(&mut emu(RngAlgorithm::Xorshift, 1234), 8), expected);
+}
+
+#[test]
+fn reset_starts_the_sequence_again() {
+    for algorithm in [RngAlgorithm::ChaCha8, RngAlgorithm::Xorshift] {
+        let mut emu = emu(algorithm, 7);
+        let first = bytes(&mut emu, 8);
+        let rom: Vec<u8> = emu.ram()[START_ADDR as usize..START_ADDR as usize + 16].to_vec();
+        emu.reset();
+        emu.load(&rom).unwrap();
+        assert_eq!(bytes(&mut emu, 8), first, "{:?}", algorithm);
+        assert_eq!(emu.seed(), 7);
+    }
+}
+
+#[test]
+fn set_seed_keeps_the_algorithm() {
+    let mut emu = emu(RngAlgorithm::Xorshift, 1);
+    emu.set_seed(1234);
+    assert_eq!(emu.rng_algorithm(), Some(RngAlgorithm::Xorshift));
+    assert_eq!(bytes(&mut emu, 8), bytes(&mut self::emu(RngAlgorithm::Xorshift, 1234), 8));
+}
+
+#[test]
+fn states_carry_the_generator_on() {
+    for algorithm in [RngAlgorithm::ChaCha8, RngAlgorithm::Xorshift] {
+        let mut emu = emu(algorithm, 99);
+        bytes(&mut emu, 3);
+        let state = emu.save_state();
+        let after = bytes(&mut emu, 5);
+
+        let mut other = self::emu(RngAlgorithm::default(), 0);
+        other.load_state(&state).unwrap();
+        assert_eq!(other.rng_algorithm(), Some(algorithm));
+        assert_eq!(bytes(&mut other, 5), after, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn custom_generators_survive_loading_a_state() {
+    let mut emu = emu(RngAlgorithm::ChaCha8, 0);
+    emu.set_custom_rng(Box::new(Counter(0)));
+    assert_eq!(emu.rng_algorithm(), None);
+    assert_eq!(bytes(&mut emu, 2), [1, 2]);
+    let state = emu.save_state();
+    assert_eq!(bytes(&mut emu, 2), [3, 4]);
+
+    // The state can't hold the generator, so the one plugged in carries on
+    emu.load_state(&state).unwrap();
+    assert_eq!(emu.rng_algorithm(), None);
+    assert_eq!(bytes(&mut emu, 2), [5, 6]);
+}
@@ -0,0 +1,84 @@
+use chip8_core::movie::sha1_hex;
+use chip8_core::romdb::{self, RomDatabase};
+use chip8_core::screenshot::DEFAULT_PALETTE;
+use chip8_core::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn rom(name: &str) -> Vec<u8> {
+    fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms").join(name).with_extension("ch8")).unwrap()
+}
+
+#[test]
+fn bundled_database_knows_the_test_roms() {
+    let db = RomDatabase::bundled();
+    let keypad = db.lookup(&rom("keypad")).unwrap();
+    assert_eq!(keypad.title.as_deref(), Some("Keypad test"));
+    assert_eq!(keypad.mode, Some(Mode::Chip8));
+    assert_eq!(keypad.quirks, Some(Quirks::vip()));
+    assert_eq!(keypad.ips, Some(600));
+    for name in ["opcodes", "flags", "quirks"] {
+        assert!(db.lookup(&rom(name)).is_some(), "{} isn't in the bundled database", name);
+    }
+    assert!(db.lookup(b"not a known rom").is_none());
+}
+
+#[test]
+fn platforms_and_quirks_map_onto_ours() {
+    let db = RomDatabase::parse(r##"[{ "title": "Game", "roms": {
+        "AAAA": { "platforms": ["megachip8", "superchip"],
+                  "quirkyPlatforms": { "superchip": { "shift": false, "wrap": true, "logic": true } },
+                  "keys": { "up": 5, "a": 6 },
+                  "colors": { "pixels": ["#101010", "#ffcc00"] } },
+        "bbbb": { "platforms": ["chip48"], "quirkyPlatforms": { "chip48": { "memoryIncrementByX": false } } }
+    } }]"##).unwrap();
+    assert_eq!(db.len(), 2);
+
+    // megachip8 isn't supported, so the next platform decides. Hashes match in any case
+    let game = db.get("aaaa").unwrap();
+    assert_eq!(game.mode, Some(Mode::SuperChip));
+    assert_eq!(game.quirks, Some(Quirks { vf_reset: true, memory: false, shift: false, jump: true, clip: false }));
+    assert_eq!(game.ips, Some(30 * 60));
+    assert_eq!((game.key("up"), game.key("a"), game.key("b")), (Some(5), Some(6), None));
+    let palette = game.palette.unwrap();
+    assert_eq!(&palette[..2], &[(0x10, 0x10, 0x10), (0xff, 0xcc, 0x00)]);
+    assert_eq!(&palette[2..], &DEFAULT_PALETTE[2..]);
+
+    let other = db.get("BBBB").unwrap();
+    assert_eq!(other.mode, Some(Mode::Chip8), "CHIP-48 has no SUPER-CHIP opcodes");
+    assert_eq!(other.quirks, Some(Quirks { memory: true, ..Quirks::chip48() }));
+}
+
+#[test]
+fn local_override_wins_field_by_field() {
+    let mut db = RomDatabase::bundled();
+    let json = format!(r#"[{{ "roms": {{ "{}": {{ "tickrate": 20 }} }} }}]"#, sha1_hex(&rom("keypad")));
+    let local = RomDatabase::parse(&json).unwrap();
+    db.extend(local);
+
+    let keypad = db.lookup(&rom("keypad")).unwrap();
+    assert_eq!(keypad.ips, Some(1200));
+    assert_eq!(keypad.title.as_deref(), Some("Keypad test"));
+    assert_eq!(keypad.quirks, Some(Quirks::vip()));
+}
+
+#[test]
+fn bad_files_are_reported() {
+    let err = RomDatabase::parse("[\n{ \"roms\": }").unwrap_err();
+    assert!(matches!(err, DatabaseError::Json { line: 2, .. }), "{}", err);
+    let err = RomDatabase::parse(r#"[{ "roms": { "aa": { "keys": { "up": 16 } } } }]"#).unwrap_err();
+    assert_eq!(err.to_string(), "ROM aa: bad key for up");
+    for tickrate in ["0", "-1", "2.5", "\"20\"", "4294967296"] {
+        let err = RomDatabase::parse(&format!(r#"[{{ "roms": {{ "aa": {{ "tickrate": {} }} }} }}]"#, tickrate)).unwrap_err();
+        assert_eq!(err.to_string(), format!("ROM aa: tickrate {} isn't a positive whole number", tickrate));
+    }
+}
+
+#[test]
+fn local_files_sit_in_the_config_dir_and_next_to_the_rom() {
+    let paths = romdb::local_paths(Path::new("games/pong.ch8"));
+    assert_eq!(paths.last().unwrap(), Path::new("games/programs.json"));
+    for path in &paths[..paths.len() - 1] {
+        assert!(path.ends_with("chip8/programs.json"), "{}", path.display());
+    }
+}
@@ -0,0 +1,46 @@
+; Result grid shared by the test ROMs, INCLUDE it after the program's last instruction.
+;
+; CALL banner first draws the program's name along the bottom of the screen, V1 sprites of
+; 8x5 starting at I, so each ROM's screen can be told apart. It uses V0 and V1 as well.
+;
+; Each CALL check draws the check number (VB) followed by a tick when V0 equals V1 or a
+; cross when it doesn't, four checks to a row. Uses VB, VD, VE, VF and I, the programs keep
+; to the other registers.
+
+check:  LD F, VB
+        DRW VD, VE, 5
+        ADD VD, 5
+        LD I, tick
+        SE V0, V1
+        LD I, cross
+        DRW VD, VE, 5
+        ADD VD, 11
+        ADD VB, 1
+        SE VD, 64
+        RET
+        LD VD, 0
+        ADD VE, 6
+        RET
+
+banner: LD VD, 0
+        LD VE, 27
+        LD V0, 5
+tile:   DRW VD, VE, 5
+        ADD VD, 8
+        ADD I, V0
+        ADD V1, 0xFF
+        SE V1, 0
+        JP tile
+        RET
+
+tick:   DB 0b00000001
+        DB 0b00000010
+        DB 0b10000100
+        DB 0b01001000
+        DB 0b00110000
+
+cross:  DB 0b10001000
+        DB 0b01010000
+        DB 0b00100000
+        DB 0b01010000
+        DB 0b10001000
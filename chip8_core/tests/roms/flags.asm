@@ -0,0 +1,147 @@
+; Flags test: VF after arithmetic, shifts and drawing, including VF as the destination.
+; See check.asm for the result grid.
+
+        CLS
+        LD I, name              ; FLAGS along the bottom
+        LD V1, 3
+        CALL banner
+        LD VB, 0
+        LD VD, 0
+        LD VE, 0
+
+        ; 0, 1: 8XY4 carry and the wrapped result
+        LD V2, 0xFF
+        LD V3, 0x01
+        ADD V2, V3
+        LD V0, VF
+        LD V1, 1
+        CALL check
+        LD V0, V2
+        LD V1, 0
+        CALL check
+
+        ; 2: 8XY4 without carry
+        LD V2, 0x10
+        ADD V2, V3
+        LD V0, VF
+        LD V1, 0
+        CALL check
+
+        ; 3: 8XY5 without borrow sets VF
+        LD V2, 5
+        LD V3, 3
+        SUB V2, V3
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+        ; 4, 5: 8XY5 with borrow clears VF and wraps
+        LD V2, 3
+        LD V3, 5
+        SUB V2, V3
+        LD V0, VF
+        LD V1, 0
+        CALL check
+        LD V0, V2
+        LD V1, 0xFE
+        CALL check
+
+        ; 6: 8XY7 without borrow
+        LD V2, 3
+        LD V3, 5
+        SUBN V2, V3
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+        ; 7: 8XY7 with borrow
+        LD V2, 5
+        LD V3, 3
+        SUBN V2, V3
+        LD V0, VF
+        LD V1, 0
+        CALL check
+
+        ; 8, 9: 8XY6 shifts the low bit out into VF
+        LD V2, 0x05
+        SHR V2
+        LD V0, VF
+        LD V1, 1
+        CALL check
+        LD V2, 0x04
+        SHR V2
+        LD V0, VF
+        LD V1, 0
+        CALL check
+
+        ; A, B: 8XYE shifts the high bit out into VF
+        LD V2, 0x80
+        SHL V2
+        LD V0, VF
+        LD V1, 1
+        CALL check
+        LD V2, 0x40
+        SHL V2
+        LD V0, VF
+        LD V1, 0
+        CALL check
+
+        ; C: 8FY4, the carry wins over the sum
+        LD VF, 0xFF
+        LD V2, 1
+        ADD VF, V2
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+        ; D: 8FY5, no borrow wins over the difference
+        LD VF, 5
+        LD V2, 3
+        SUB VF, V2
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+        ; E: 7XNN leaves VF alone even when it overflows
+        LD VF, 5
+        LD V2, 0xFF
+        ADD V2, 2
+        LD V0, VF
+        LD V1, 5
+        CALL check
+
+        ; F: DXYN sets VF on collision and clears it otherwise
+        LD V2, 0
+        LD F, V2
+        LD V3, 40               ; Clear of the name along the bottom
+        LD V4, 26
+        DRW V3, V4, 5
+        LD V5, VF
+        DRW V3, V4, 5
+        LD V6, VF
+        LD V0, V5
+        SHL V0
+        ADD V0, V6              ; First VF times 2 plus the second, 0 then 1 makes 1
+        LD V1, 1
+        CALL check
+
+done:   JP done
+
+; FLAGS, two letters to a sprite
+name:   DB 0b11101000
+        DB 0b10001000
+        DB 0b11001000
+        DB 0b10001000
+        DB 0b10001110
+        DB 0b01001110
+        DB 0b10101000
+        DB 0b11101010
+        DB 0b10101010
+        DB 0b10101110
+        DB 0b11100000
+        DB 0b10000000
+        DB 0b11100000
+        DB 0b00100000
+        DB 0b11100000
+
+INCLUDE "check.asm"
@@ -0,0 +1,66 @@
+; Keypad test, played with keypad.keys: FX0A waits for three presses, each one is checked
+; and then waited out with EXA1 so a held key isn't read twice. Then EX9E and EXA1 are
+; checked against key A, which the script holds down, and key B, which it doesn't.
+; See check.asm for the result grid.
+
+        CLS
+        LD I, name              ; KEYPAD along the bottom
+        LD V1, 3
+        CALL banner
+        LD VB, 0
+        LD VD, 0
+        LD VE, 0
+
+        ; 0, 1, 2: FX0A reads 1, 7 then C
+        LD V1, 0x1
+        CALL key
+        LD V1, 0x7
+        CALL key
+        LD V1, 0xC
+        CALL key
+
+        ; 3: EX9E skips while A is held
+        LD V2, 0xA
+wait:   SKP V2
+        JP wait
+        LD V0, 1
+        LD V1, 1
+        CALL check
+
+        ; 4: EXA1 skips while B isn't
+        LD V2, 0xB
+        LD V0, 0
+        SKNP V2
+        JP held
+        LD V0, 1
+held:   LD V1, 1
+        CALL check
+
+done:   JP done
+
+        ; Wait for a key, check it against V1, then wait for it to be let go
+key:    LD V0, K
+        CALL check
+release:
+        SKNP V0
+        JP release
+        RET
+
+; KEYPAD, two letters to a sprite
+name:   DB 0b10101110
+        DB 0b10101000
+        DB 0b11001100
+        DB 0b10101000
+        DB 0b10101110
+        DB 0b10101110
+        DB 0b10101010
+        DB 0b01001110
+        DB 0b01001000
+        DB 0b01001000
+        DB 0b01001100
+        DB 0b10101010
+        DB 0b11101010
+        DB 0b10101010
+        DB 0b10101100
+
+INCLUDE "check.asm"
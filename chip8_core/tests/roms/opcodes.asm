@@ -0,0 +1,184 @@
+; Opcode test: each check runs one group of instructions and compares what comes out with
+; the answer every interpreter should agree on, whatever its quirks. See check.asm for the
+; result grid. The bottom right of the screen is scratch space for the drawing check.
+
+        CLS
+        LD I, name              ; OPCODES along the bottom
+        LD V1, 4
+        CALL banner
+        LD VB, 0
+        LD VD, 0
+        LD VE, 0
+
+        ; 0: 6XNN, 7XNN
+        LD V0, 0x12
+        ADD V0, 0x34
+        LD V1, 0x46
+        CALL check
+
+        ; 1: 8XY0
+        LD V2, 0x5A
+        LD V0, V2
+        LD V1, 0x5A
+        CALL check
+
+        ; 2: 8XY1
+        LD V0, 0x0F
+        LD V2, 0xF0
+        OR V0, V2
+        LD V1, 0xFF
+        CALL check
+
+        ; 3: 8XY2
+        LD V0, 0x3C
+        LD V2, 0x0F
+        AND V0, V2
+        LD V1, 0x0C
+        CALL check
+
+        ; 4: 8XY3
+        LD V0, 0x3C
+        LD V2, 0x0F
+        XOR V0, V2
+        LD V1, 0x33
+        CALL check
+
+        ; 5: 8XY4
+        LD V0, 0x10
+        LD V2, 0x20
+        ADD V0, V2
+        LD V1, 0x30
+        CALL check
+
+        ; 6: 8XY5
+        LD V0, 0x30
+        LD V2, 0x10
+        SUB V0, V2
+        LD V1, 0x20
+        CALL check
+
+        ; 7: 8XY7
+        LD V0, 0x10
+        LD V2, 0x30
+        SUBN V0, V2
+        LD V1, 0x20
+        CALL check
+
+        ; 8: 3XNN, 4XNN, 5XY0, 9XY0, each counts 1 when it skips or doesn't the way it should
+        LD V0, 0
+        LD V2, 5
+        LD V3, 5
+        LD V4, 6
+        SE V2, 5
+        JP skip1
+        ADD V0, 1
+skip1:  SNE V2, 6
+        JP skip2
+        ADD V0, 1
+skip2:  SE V2, V3
+        JP skip3
+        ADD V0, 1
+skip3:  SNE V2, V4
+        JP skip4
+        ADD V0, 1
+skip4:  LD V1, 4
+        CALL check
+
+        ; 9: 2NNN, 00EE
+        LD V0, 0
+        CALL sub
+        LD V1, 0x77
+        CALL check
+
+        ; A: FX33, FX65
+        LD V2, 123
+        LD I, buffer
+        LD B, V2
+        LD I, buffer
+        LD V2, [I]
+        SHL V0
+        SHL V0
+        ADD V0, V1
+        SHL V0
+        SHL V0
+        ADD V0, V2
+        LD V1, 0x1B             ; (1 * 4 + 2) * 4 + 3
+        CALL check
+
+        ; B: ANNN, FX1E, FX55
+        LD V0, 0xA5
+        LD V1, 0x5A
+        LD I, buffer
+        LD V2, 1
+        ADD I, V2
+        LD [I], V1
+        LD V0, 0
+        LD V1, 0
+        LD I, buffer
+        LD V2, [I]
+        LD V0, V2
+        LD V1, 0x5A
+        CALL check
+
+        ; C: CXNN masks its random byte
+        RND V0, 0
+        LD V1, 0
+        CALL check
+
+        ; D: FX15, FX07, the delay timer counts down to 0
+        LD V2, 3
+        LD DT, V2
+wait:   LD V0, DT
+        SE V0, 0
+        JP wait
+        LD V1, 0
+        CALL check
+
+        ; E: FX29, DXYN, drawing the same digit twice collides and leaves nothing behind
+        LD V2, 8
+        LD F, V2
+        LD V3, 40               ; Clear of the name along the bottom
+        LD V4, 26
+        DRW V3, V4, 5
+        DRW V3, V4, 5
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+        ; F: 8XY6, 8XYE on one register, the same either way around the shift quirk
+        LD V0, 0x84
+        SHR V0
+        SHL V0
+        LD V1, 0x84
+        CALL check
+
+done:   JP done
+
+sub:    LD V0, 0x77
+        RET
+
+buffer: DB 0, 0, 0
+
+; OPCODES, two letters to a sprite
+name:   DB 0b11101110
+        DB 0b10101010
+        DB 0b10101110
+        DB 0b10101000
+        DB 0b11101000
+        DB 0b11101110
+        DB 0b10001010
+        DB 0b10001010
+        DB 0b10001010
+        DB 0b11101110
+        DB 0b11001110
+        DB 0b10101000
+        DB 0b10101100
+        DB 0b10101000
+        DB 0b11001110
+        DB 0b11100000
+        DB 0b10000000
+        DB 0b11100000
+        DB 0b00100000
+        DB 0b11100000
+
+INCLUDE "check.asm"
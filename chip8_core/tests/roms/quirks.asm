@@ -0,0 +1,99 @@
+; Quirks test: works out which quirks the interpreter has. Each check shows a tick when the
+; quirk is on and a cross when it's off, in the order vf_reset, memory, shift, jump, clip.
+; See check.asm for the result grid.
+
+        JP main
+
+        ; BNNN lands here, 0x202 plus V0 without the jump quirk or plus V2 with it
+table:  JP jump_off
+        JP jump_on
+
+main:   CLS
+        LD I, name              ; QUIRKS along the bottom
+        LD V1, 3
+        CALL banner
+        LD VB, 0
+        LD VD, 0
+        LD VE, 0
+        LD V1, 1
+
+        ; 0: vf_reset, 8XY1 clears VF
+        LD VF, 5
+        LD V2, 0
+        OR V2, V2
+        LD V0, 0
+        SNE VF, 0
+        LD V0, 1
+        CALL check
+
+        ; 1: memory, FX55 moves I past what it stored, so FX65 reads the next byte
+        LD I, buffer
+        LD V0, 0xAA
+        LD [I], V0
+        LD V0, [I]
+        LD V2, V0
+        LD V0, 0
+        SNE V2, 0x55
+        LD V0, 1
+        CALL check
+
+        ; 2: shift, 8XY6 shifts VX instead of VY
+        LD V2, 0x10
+        LD V3, 0x04
+        SHR V2, V3
+        LD V0, 0
+        SNE V2, 0x08
+        LD V0, 1
+        CALL check
+
+        ; 3: jump, BNNN adds VX instead of V0
+        LD V0, 0
+        LD V2, 2
+        JP V0, table
+jump_off:
+        LD V0, 0
+        JP jumped
+jump_on:
+        LD V0, 1
+jumped: CALL check
+
+        ; 4: clip, sprites past the right edge are cut off instead of wrapping round
+        LD I, line
+        LD V2, 60
+        LD V3, 26
+        DRW V2, V3, 1
+        LD I, dot
+        LD V4, 0
+        DRW V4, V3, 1
+        LD V0, 0
+        SNE VF, 0
+        LD V0, 1
+        DRW V4, V3, 1           ; Clean up
+        LD I, line
+        DRW V2, V3, 1
+        CALL check
+
+done:   JP done
+
+buffer: DB 0xAA, 0x55
+line:   DB 0xFF
+dot:    DB 0x80
+
+; QUIRKS, two letters to a sprite
+name:   DB 0b11101010
+        DB 0b10101010
+        DB 0b10101010
+        DB 0b11101010
+        DB 0b00101110
+        DB 0b11101100
+        DB 0b01001010
+        DB 0b01001100
+        DB 0b01001010
+        DB 0b11101010
+        DB 0b10101110
+        DB 0b10101000
+        DB 0b11001110
+        DB 0b10100010
+        DB 0b10101110
+
+INCLUDE "check.asm"
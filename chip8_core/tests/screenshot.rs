@@ -0,0 +1,50 @@
+use chip8_core::asm::assemble;
+use chip8_core::screenshot::{ImageFormat, Screenshot, DEFAULT_PALETTE};
+use chip8_core::*;
+use std::path::Path;
+
+// The 1 glyph in the top left corner
+fn one() -> Emu {
+    let mut emu = Emu::new();
+    emu.load(&assemble("LD V1, 1\nLD F, V1\nDRW V0, V0, 5").unwrap()).unwrap();
+    for _ in 0..3 {
+        emu.tick().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn pbm_is_one_line_per_row() {
+    let pbm = Screenshot { format: ImageFormat::Pbm, scale: 2, ..Screenshot::default() }.encode(&one());
+    let text = String::from_utf8(pbm).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[..2], ["P1", "128 64"]);
+    assert_eq!(lines.len(), 2 + 64);
+    assert!(lines[2..].iter().all(|row| row.len() == 128));
+    // Glyph row 0x20 is ..#., doubled
+    assert_eq!(&lines[2][..8], "00001100");
+    assert_eq!(&lines[3][..8], "00001100");
+    assert_eq!(&lines[4][..8], "00111100");
+}
+
+#[test]
+fn png_uses_the_palette() {
+    let mut palette = DEFAULT_PALETTE;
+    palette[1] = (255, 0, 0);
+    let data = Screenshot { scale: 3, palette, ..Screenshot::default() }.encode(&one());
+
+    let decoder = png::Decoder::new(data.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (64 * 3, 32 * 3));
+    assert_eq!(&info.palette.as_ref().unwrap()[3..6], &[255, 0, 0]);
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    let row: Vec<u8> = pixels[..12].to_vec();
+    assert_eq!(row, [0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0]);
+
+    assert_eq!(ImageFormat::from_path(Path::new("shot.PBM")), Some(ImageFormat::Pbm));
+    assert_eq!(ImageFormat::from_path(Path::new("shot")), None);
+}
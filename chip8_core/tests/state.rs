@@ -0,0 +1,136 @@
+use chip8_core::*;
+use std::fs;
+use std::path::PathBuf;
+
+// Byte offsets into a state, see save_state for the layout
+const VERSION: usize = 4;
+const MODE: usize = 6;
+const HIRES: usize = 66;
+const PLANES: usize = 68;
+const PITCH: usize = 69;
+
+// Draws a 0, then takes two random bytes into V2 and V3
+const PROGRAM: [u16; 6] = [0x6012, 0x6134, 0xA000, 0xD015, 0xC2FF, 0xC3FF];
+
+fn emu(mode: Mode, seed: u64) -> Emu {
+    let rom: Vec<u8> = PROGRAM.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut emu = Emu::with_quirks(mode.quirks());
+    emu.set_mode(mode);
+    emu.set_seed(seed);
+    emu.load(&rom).unwrap();
+    emu
+}
+
+fn run(emu: &mut Emu, n: usize) {
+    for _ in 0..n {
+        emu.tick().unwrap();
+    }
+}
+
+fn load(data: &[u8]) -> Result<Emu, StateError> {
+    let mut emu = emu(Mode::Chip8, 0);
+    emu.load_state(data).map(|_| emu)
+}
+
+#[test]
+fn states_round_trip() {
+    for mode in [Mode::Chip8, Mode::SuperChip, Mode::XoChip] {
+        let mut saved = emu(mode, 1234);
+        run(&mut saved, 5);
+        saved.keypress(7, true);
+        let state = saved.save_state();
+
+        let mut restored = load(&state).unwrap();
+        assert_eq!(restored.save_state(), state, "{:?}", mode);
+        assert_eq!((restored.mode(), restored.quirks(), restored.seed()), (mode, mode.quirks(), 1234));
+
+        // The RNG carries on where it was
+        run(&mut saved, 1);
+        run(&mut restored, 1);
+        assert_eq!(restored.registers(), saved.registers(), "{:?}", mode);
+    }
+}
+
+#[test]
+fn other_data_is_not_a_state() {
+    let state = emu(Mode::Chip8, 0).save_state();
+    let mut data = state.clone();
+    data[..4].copy_from_slice(b"C8SS");
+    assert_eq!(load(&data).err(), Some(StateError::BadMagic));
+    assert_eq!(load(b"").err(), Some(StateError::Truncated));
+
+    for version in [0, 3, u16::MAX] {
+        let mut data = state.clone();
+        data[VERSION..VERSION + 2].copy_from_slice(&version.to_le_bytes());
+        assert_eq!(load(&data).err(), Some(StateError::UnsupportedVersion(version)));
+    }
+}
+
+#[test]
+fn truncated_and_padded_states_are_rejected() {
+    let state = emu(Mode::XoChip, 0).save_state();
+    for len in [5, 70, 200, state.len() - 1] {
+        assert_eq!(load(&state[..len]).err(), Some(StateError::Truncated), "{} bytes", len);
+    }
+    let mut data = state.clone();
+    data.push(0);
+    assert_eq!(load(&data).err(), Some(StateError::Invalid("length")));
+}
+
+#[test]
+fn failed_loads_leave_the_emulator_alone() {
+    let mut emu = emu(Mode::Chip8, 0);
+    run(&mut emu, 4);
+    let before = emu.save_state();
+    assert!(emu.load_state(&before[..before.len() - 1]).is_err());
+    assert_eq!(emu.save_state(), before);
+}
+
+#[test]
+fn fields_have_to_fit_the_mode() {
+    let chip8 = emu(Mode::Chip8, 0).save_state();
+    let xochip = emu(Mode::XoChip, 0).save_state();
+    let with = |state: &[u8], at: usize, value: u8| {
+        let mut data = state.to_vec();
+        data[at] = value;
+        load(&data).err()
+    };
+
+    assert_eq!(with(&chip8, MODE, 3), Some(StateError::Invalid("mode")));
+    assert_eq!(with(&chip8, HIRES, 1), Some(StateError::Invalid("resolution")));
+    assert_eq!(with(&chip8, HIRES, 2), Some(StateError::Invalid("flag")));
+    assert_eq!(with(&chip8, PLANES, 3), Some(StateError::Invalid("plane mask")));
+    assert_eq!(with(&chip8, PITCH, 100), Some(StateError::Invalid("pitch")));
+    assert_eq!(with(&xochip, PLANES, 0x10), Some(StateError::Invalid("plane mask")));
+
+    // Everything XO-CHIP can really be in loads
+    assert_eq!(with(&xochip, HIRES, 1), None);
+    assert_eq!(with(&xochip, PLANES, 0xF), None);
+    assert_eq!(with(&xochip, PITCH, 100), None);
+}
+
+#[test]
+fn version_1_states_migrate() {
+    // Saved by a version 1 core after the first 5 instructions of PROGRAM with the RNG seeded
+    // with 1234. Version 1 kept the ChaCha8 state but not the seed
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/states/chip8-v1.state");
+    let data = fs::read(path).unwrap();
+    assert_eq!(data[VERSION..VERSION + 2], 1u16.to_le_bytes());
+
+    let mut migrated = load(&data).unwrap();
+    let mut fresh = emu(Mode::Chip8, 1234);
+    run(&mut fresh, 5);
+    assert_eq!(migrated.seed(), 0);
+    assert_eq!(migrated.pc(), fresh.pc());
+    assert_eq!(migrated.get_display(), fresh.get_display());
+    assert_eq!(migrated.registers(), fresh.registers());
+
+    run(&mut migrated, 1);
+    run(&mut fresh, 1);
+    assert_eq!(migrated.registers(), fresh.registers(), "the RNG carries on");
+
+    // Saving writes the current version
+    let state = migrated.save_state();
+    assert_eq!(state[VERSION..VERSION + 2], 2u16.to_le_bytes());
+    assert!(load(&state).is_ok());
+}
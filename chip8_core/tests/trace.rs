@@ -0,0 +1,77 @@
+use chip8_core::asm::assemble;
+use chip8_core::disasm::Syntax;
+use chip8_core::trace::{read_binary, TraceFormat, TraceRecord, Tracer};
+use chip8_core::*;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+// Writer the test can read back after the tracer is done with it
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn traced(format: TraceFormat) -> Vec<u8> {
+    let out = Shared::default();
+    let mut emu = Emu::new();
+    emu.load(&assemble("LD V0, 5\nLD I, 0x300\nADD I, V0").unwrap()).unwrap();
+    emu.set_tracer(Tracer::new(Box::new(out.clone()), format, Syntax::Cowgod).unwrap());
+    for _ in 0..3 {
+        emu.tick().unwrap();
+    }
+    let tracer = emu.take_tracer().unwrap();
+    assert_eq!(tracer.cycles(), 3);
+    tracer.finish().unwrap();
+
+    emu.tick().unwrap();    // Not traced any more
+    let data = out.0.lock().unwrap().clone();
+    data
+}
+
+#[test]
+fn text_trace_has_a_line_per_instruction() {
+    let text = String::from_utf8(traced(TraceFormat::Text)).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let zeros = "00".repeat(16);
+    let v0 = format!("05{}", "00".repeat(15));
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], format!("         0 0200: 6005  LD V0, 0x05            V={} I=0000 -> V={} I=0000", zeros, v0));
+    assert!(lines[2].starts_with("         2 0204: F01E  ADD I, V0"));
+    assert!(lines[2].ends_with(&format!("I=0300 -> V={} I=0305", v0)));
+}
+
+#[test]
+fn binary_trace_round_trips() {
+    let records = read_binary(&traced(TraceFormat::Binary)).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1].cycle, 1);
+    assert_eq!(records[1].pc, 0x202);
+    assert_eq!(records[1].opcode, 0xA300);
+    assert_eq!((records[1].i_before, records[1].i_after), (0, 0x300));
+    assert_eq!(records[2].disassembly(Syntax::Octo), "i += v0");
+
+    assert!(read_binary(b"C8TR\x01\x00\x00").is_err());
+    assert!(read_binary(b"nope").is_err());
+}
+
+#[test]
+fn text_columns_line_up_above_0xfff() {
+    let record = |pc| TraceRecord { cycle: 0, pc, opcode: 0x00E0, next: 0, mode: Mode::XoChip,
+        v_before: [0; 16], i_before: 0, v_after: [0; 16], i_after: 0 };
+    let line = |pc| {
+        let mut out = Vec::new();
+        record(pc).write_text(&mut out, Syntax::Cowgod).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(line(0x200).find("CLS"), line(0xBEEF).find("CLS"));
+    assert!(line(0xBEEF).contains(" BEEF: 00E0 "));
+}
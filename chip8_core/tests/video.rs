@@ -0,0 +1,100 @@
+use chip8_core::asm::assemble;
+use chip8_core::screenshot::DEFAULT_PALETTE;
+use chip8_core::video::{Recorder, VideoFormat};
+use chip8_core::*;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+// Writer the test can read back after the recorder is done with it
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Three frames of a blank screen, then one with a sprite
+fn record(format: VideoFormat) -> Vec<u8> {
+    let out = Shared::default();
+    let mut recorder = Recorder::new(Box::new(out.clone()), format, 1, &DEFAULT_PALETTE).unwrap();
+    let mut emu = Emu::new();
+    emu.load(&assemble("DRW V0, V0, 5").unwrap()).unwrap();
+    for _ in 0..3 {
+        recorder.frame(&emu).unwrap();
+    }
+    emu.tick().unwrap();
+    recorder.frame(&emu).unwrap();
+    assert_eq!(recorder.frames(), 4);
+    recorder.finish().unwrap();
+    let data = out.0.lock().unwrap().clone();
+    data
+}
+
+// Delays and the last frame's pixels
+fn gif_frames(data: &[u8]) -> (Vec<u16>, Vec<u8>) {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(data).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (HIRES_WIDTH as u16, HIRES_HEIGHT as u16));
+
+    let mut delays = Vec::new();
+    let mut last = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+        last = frame.buffer.to_vec();
+    }
+    (delays, last)
+}
+
+#[test]
+fn gif_merges_repeated_frames() {
+    let (delays, last) = gif_frames(&record(VideoFormat::Gif));
+    // 3 ticks end on the 3rd 50th, 6 hundredths. The 4th doesn't reach another 50th but is
+    // the last frame, so it still shows
+    assert_eq!(delays, [6, 2]);
+    // Low resolution pixels are doubled, the 0 glyph's top row is ####
+    assert_eq!(&last[..10], &[1, 1, 1, 1, 1, 1, 1, 1, 0, 0]);
+    assert_eq!(&last[HIRES_WIDTH..HIRES_WIDTH + 10], &[1, 1, 1, 1, 1, 1, 1, 1, 0, 0]);
+}
+
+#[test]
+fn gif_keeps_real_time_with_delays_viewers_honour() {
+    // A sprite that flips on and off every tick, a second of it
+    let out = Shared::default();
+    let mut recorder = Recorder::new(Box::new(out.clone()), VideoFormat::Gif, 1, &DEFAULT_PALETTE).unwrap();
+    let mut emu = Emu::new();
+    emu.load(&assemble("loop: DRW V0, V0, 5\nJP loop").unwrap()).unwrap();
+    for _ in 0..60 {
+        emu.tick().unwrap();
+        emu.tick().unwrap();
+        recorder.frame(&emu).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let (delays, _) = gif_frames(&out.0.lock().unwrap());
+    assert!(delays.iter().all(|&delay| delay >= 2), "{:?}", delays);
+    assert_eq!(delays.iter().map(|&delay| delay as u32).sum::<u32>(), 100);
+    assert_eq!(delays.len(), 50);
+}
+
+#[test]
+fn y4m_has_a_frame_per_tick() {
+    let data = record(VideoFormat::Y4m);
+    let header = b"YUV4MPEG2 W128 H64 F60:1 Ip A1:1 C444\n";
+    assert!(data.starts_with(header));
+
+    let frame_size = b"FRAME\n".len() + 3 * HIRES_WIDTH * HIRES_HEIGHT;
+    assert_eq!(data.len(), header.len() + 4 * frame_size);
+    let last = &data[header.len() + 3 * frame_size..];
+    assert!(last.starts_with(b"FRAME\n"));
+    let plane = HIRES_WIDTH * HIRES_HEIGHT;
+    let pixel = |at: usize| [last[6 + at], last[6 + plane + at], last[6 + 2 * plane + at]];
+    assert_eq!(pixel(0), [235, 128, 128], "white is the top of limited range");
+    assert_eq!(pixel(8), [16, 128, 128], "black is the bottom");
+}
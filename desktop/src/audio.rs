@@ -0,0 +1,104 @@
+/*
+    Audio output : SDL playback, or a WAV dump for machines without a sound card
+ */
+use chip8_core::audio::{SquareWave, WavWriter, SAMPLE_RATE};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+const FRAME_RATE: u32 = 60;     // frame() is called once per tick_timers
+
+// Runs on SDL's audio thread, the main loop flips `on` through the device lock
+pub struct ToneCallback {
+    wave: SquareWave,
+    sample_rate: u32,
+    on: bool,
+}
+
+impl AudioCallback for ToneCallback {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.wave.fill(out, self.sample_rate, self.on);
+    }
+}
+
+pub enum Audio {
+    Sdl(AudioDevice<ToneCallback>),
+    Wav {
+        writer: WavWriter<BufWriter<File>>,
+        wave: SquareWave,
+        buffer: Vec<f32>,       // One frame of samples
+    },
+}
+
+impl Audio {
+    pub fn sdl(sdl_context: &Sdl, wave: SquareWave) -> Result<Self, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio_subsystem.open_playback(None, &desired, |spec| ToneCallback {
+            wave,
+            sample_rate: spec.freq as u32,
+            on: false,
+        })?;
+        device.resume();
+        Ok(Audio::Sdl(device))
+    }
+
+    pub fn wav(path: &Path, wave: SquareWave) -> io::Result<Self> {
+        let writer = WavWriter::new(BufWriter::new(File::create(path)?), SAMPLE_RATE)?;
+        Ok(Audio::Wav {
+            writer,
+            wave,
+            buffer: vec![0.0; (SAMPLE_RATE / FRAME_RATE) as usize],
+        })
+    }
+
+    // Called once per frame with the sound timer state
+    pub fn frame(&mut self, beeping: bool) -> io::Result<()> {
+        match self {
+            Audio::Sdl(device) => device.lock().on = beeping,
+            Audio::Wav { writer, wave, buffer } => {
+                wave.fill(buffer, SAMPLE_RATE, beeping);
+                writer.write(buffer)?;
+            },
+        }
+        Ok(())
+    }
+
+    // Stop the tone without writing anything, for stretches where no frames run
+    pub fn silence(&mut self) {
+        if let Audio::Sdl(device) = self {
+            device.lock().on = false;
+        }
+    }
+
+    // Returns whether the tone is now muted
+    pub fn toggle_mute(&mut self) -> bool {
+        match self {
+            Audio::Sdl(device) => {
+                let mut callback = device.lock();
+                callback.wave.muted = !callback.wave.muted;
+                callback.wave.muted
+            },
+            Audio::Wav { wave, .. } => {
+                wave.muted = !wave.muted;
+                wave.muted
+            },
+        }
+    }
+
+    // Flush the WAV header, SDL just stops when dropped
+    pub fn finish(self) -> io::Result<()> {
+        if let Audio::Wav { writer, .. } = self {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+/*
+    Debugger prompt : pause with P, then inspect and change the machine from the terminal
+
+    Commands are read on a separate thread so the window keeps drawing and handling events
+    while the prompt waits. Numbers are hex, with or without 0x.
+ */
+use chip8_core::disasm::{self, Syntax};
+use chip8_core::*;
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+const HELP: &str = "\
+c | continue            resume
+s | step [N]            run N instructions (default 1)
+u | until ADDR          resume until pc reaches ADDR
+r | regs                show registers
+    stack               show return addresses
+m | mem ADDR [LEN]      dump memory
+    dis [ADDR] [N]      disassemble N instructions (default pc, 8)
+b | break ADDR          break at an address
+bo PATTERN              break on an opcode pattern like 8XY4 or DXYN
+d | delete ADDR|PATTERN remove a breakpoint
+w | watch RANGE [rwx]   stop after an access to RANGE (ADDR or START-END, default w)
+    unwatch RANGE [rwx] remove a watchpoint
+l | list                show breakpoints and watchpoints
+    set REG VALUE       change V0 - VF, I, PC, DT or ST
+    poke ADDR BYTE...   write bytes to memory";
+
+pub struct Debugger {
+    pub paused: bool,
+    until: Option<u16>,                 // Target of an `until` in progress
+    commands: Option<Receiver<String>>, // Started the first time we pause so stdin is untouched otherwise
+    syntax: Syntax,
+}
+
+impl Debugger {
+    pub fn new(syntax: Syntax) -> Self {
+        Self {
+            paused: false,
+            until: None,
+            commands: None,
+            syntax,
+        }
+    }
+
+    // Run a frame's worth of instructions, pausing if a breakpoint is hit
+    pub fn run(&mut self, emu: &mut Emu, steps: usize) -> Result<(), EmuError> {
+        let stop = match self.until {
+            Some(pc) => emu.run_until(pc, steps)?,
+            None => emu.run(steps)?,
+        };
+        match stop {
+            Stop::Done | Stop::Exited => (),
+            stop => {
+                println!("Stopped: {}", stop);
+                self.pause(emu);
+            },
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self, emu: &Emu) {
+        self.paused = true;
+        self.until = None;
+        if self.commands.is_none() {
+            println!("Paused, type `help` for commands");
+            self.commands = Some(spawn_reader());
+        }
+        self.show_pc(emu);
+        prompt();
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    // Handle any commands typed since last frame
+    pub fn poll(&mut self, emu: &mut Emu) {
+        let Some(commands) = &self.commands else { return };
+        let lines: Vec<String> = commands.try_iter().collect();
+        for line in lines {
+            if !self.paused {
+                break;
+            }
+            if let Err(e) = self.command(emu, &line) {
+                println!("{}", e);
+            }
+            if self.paused {
+                prompt();
+            }
+        }
+    }
+
+    fn command(&mut self, emu: &mut Emu, line: &str) -> Result<(), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&cmd, args)) = words.split_first() else { return Ok(()) };
+        match (cmd, args) {
+            ("h" | "help", _) => println!("{}", HELP),
+            ("c" | "continue", []) => self.resume(),
+            ("s" | "step", _) => {
+                let count = match args {
+                    [] => 1,
+                    [n] => number(n)? as usize,
+                    _ => return Err("usage: step [N]".to_string()),
+                };
+                for _ in 0..count {
+                    emu.step().map_err(|e| e.to_string())?;
+                }
+                self.show_pc(emu);
+            },
+            ("u" | "until", [addr]) => {
+                self.until = Some(address(addr)?);
+                self.resume();
+            },
+            ("r" | "regs", []) => show_registers(emu),
+            ("stack", []) => {
+                for (depth, addr) in emu.stack().iter().enumerate().rev() {
+                    println!("#{} {:#05X}", depth, addr);
+                }
+            },
+            ("m" | "mem", [addr, rest @ ..]) => {
+                let addr = address(addr)? as usize;
+                let len = match rest {
+                    [] => 0x40,
+                    [len] => number(len)? as usize,
+                    _ => return Err("usage: mem ADDR [LEN]".to_string()),
+                };
+                let end = (addr + len).min(emu.ram().len());
+                for (row, bytes) in emu.ram().get(addr..end).unwrap_or(&[]).chunks(16).enumerate() {
+                    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                    println!("{:#05X}: {}", addr + row * 16, hex.join(" "));
+                }
+            },
+            ("dis", _) => {
+                let (addr, count) = match args {
+                    [] => (emu.pc(), 8),
+                    [addr] => (address(addr)?, 8),
+                    [addr, n] => (address(addr)?, number(n)? as usize),
+                    _ => return Err("usage: dis [ADDR] [N]".to_string()),
+                };
+                self.show_listing(emu, addr, count);
+            },
+            ("b" | "break", [addr]) => emu.add_breakpoint(address(addr)?),
+            ("bo", [pattern]) => match OpcodePattern::parse(pattern) {
+                Some(p) => emu.add_opcode_breakpoint(p),
+                None => return Err(format!("bad opcode pattern: {}", pattern)),
+            },
+            ("d" | "delete", [target]) => {
+                let removed = match (address(target), OpcodePattern::parse(target)) {
+                    (Ok(addr), _) if emu.remove_breakpoint(addr) => true,
+                    (_, Some(p)) => emu.remove_opcode_breakpoint(p),
+                    _ => false,
+                };
+                if !removed {
+                    return Err(format!("no breakpoint at {}", target));
+                }
+            },
+            ("w" | "watch", [range, rest @ ..]) => {
+                let range = address_range(range)?;
+                for access in accesses(rest)? {
+                    emu.add_watchpoint(range.clone(), access);
+                }
+            },
+            ("unwatch", [text, rest @ ..]) => {
+                let range = address_range(text)?;
+                let mut removed = false;
+                for access in accesses(rest)? {
+                    removed |= emu.remove_watchpoint(range.clone(), access);
+                }
+                if !removed {
+                    return Err(format!("no watchpoint on {}", text));
+                }
+            },
+            ("l" | "list", []) => {
+                for addr in emu.breakpoints() {
+                    println!("{:#05X}", addr);
+                }
+                for pattern in emu.opcode_breakpoints() {
+                    println!("{}", pattern);
+                }
+                for watch in emu.watchpoints() {
+                    println!("{} {:#05X}-{:#05X}", watch.access, watch.range.start(), watch.range.end());
+                }
+            },
+            ("set", [reg, value]) => {
+                let value = number(value)?;
+                let byte = || u8::try_from(value).map_err(|_| format!("{:#X} doesn't fit in a byte", value));
+                let reg = reg.to_ascii_lowercase();
+                match reg.as_str() {
+                    "i" => emu.set_i_reg(value as u16),
+                    "pc" => emu.set_pc(value as u16),
+                    "dt" => emu.set_delay_timer(byte()?),
+                    "st" => emu.set_sound_timer(byte()?),
+                    r if r.len() == 2 && r.starts_with('v') => {
+                        let x = u8::from_str_radix(&r[1..], 16).map_err(|_| format!("unknown register: {}", r))?;
+                        emu.set_register(x as usize, byte()?);
+                    },
+                    r => return Err(format!("unknown register: {}", r)),
+                }
+            },
+            ("poke", [addr, bytes @ ..]) if !bytes.is_empty() => {
+                let data = bytes.iter()
+                    .map(|b| number(b).and_then(|v| u8::try_from(v).map_err(|_| format!("{} doesn't fit in a byte", b))))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                emu.write_ram(address(addr)?, &data).map_err(|e| e.to_string())?;
+            },
+            _ => return Err(format!("unknown command: {} (try help)", line.trim())),
+        }
+        Ok(())
+    }
+
+    fn show_pc(&self, emu: &Emu) {
+        self.show_listing(emu, emu.pc(), 1);
+    }
+
+    fn show_listing(&self, emu: &Emu, addr: u16, count: usize) {
+        let start = addr as usize;
+        let end = (start + count * 4).min(emu.ram().len());
+        let code = emu.ram().get(start..end).unwrap_or(&[]);
+        for line in disasm::disassemble(code, addr, emu.mode(), self.syntax).iter().take(count) {
+            let marker = if line.addr == emu.pc() { "=>" } else { "  " };
+            println!("{} {:#05X}: {}", marker, line.addr, line);
+        }
+    }
+}
+
+fn show_registers(emu: &Emu) {
+    for (row, regs) in emu.registers().chunks(8).enumerate() {
+        let cells: Vec<String> = regs.iter().enumerate()
+            .map(|(i, v)| format!("V{:X}={:02X}", row * 8 + i, v))
+            .collect();
+        println!("{}", cells.join(" "));
+    }
+    println!("I={:04X} PC={:04X} SP={:X} DT={:02X} ST={:02X}",
+        emu.i_reg(), emu.pc(), emu.stack().len(), emu.delay_timer(), emu.sound_timer());
+}
+
+fn spawn_reader() -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+fn prompt() {
+    print!("(chip8) ");
+    io::stdout().flush().ok();
+}
+
+fn number(text: &str) -> Result<u32, String> {
+    let digits = text.strip_prefix("0x").or(text.strip_prefix("0X")).unwrap_or(text);
+    u32::from_str_radix(digits, 16).map_err(|_| format!("not a hex number: {}", text))
+}
+
+fn address(text: &str) -> Result<u16, String> {
+    number(text).and_then(|v| u16::try_from(v).map_err(|_| format!("address out of range: {}", text)))
+}
+
+// ADDR or START-END
+fn address_range(text: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = match text.split_once('-') {
+        Some((start, end)) => (address(start)?, address(end)?),
+        None => (address(text)?, address(text)?),
+    };
+    if start > end {
+        return Err(format!("empty range: {}", text));
+    }
+    Ok(start..=end)
+}
+
+// Letters r, w and x, writes when none are given
+fn accesses(args: &[&str]) -> Result<Vec<Access>, String> {
+    let letters = match args {
+        [] => "w",
+        [letters] => letters,
+        _ => return Err("usage: watch RANGE [rwx]".to_string()),
+    };
+    letters.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            'r' => Ok(Access::Read),
+            'w' => Ok(Access::Write),
+            'x' => Ok(Access::Execute),
+            _ => Err(format!("unknown access {} (expected r, w or x)", c)),
+        })
+        .collect()
+}
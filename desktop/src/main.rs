@@ -1,3 +1,18 @@
+mod audio;
+mod debugger;
+
+use audio::Audio;
+use debugger::Debugger;
+use chip8_core::audio::{SquareWave, DEFAULT_FREQUENCY, DEFAULT_VOLUME};
+use chip8_core::clock::{Clock, DEFAULT_IPS, TIMER_HZ};
+use chip8_core::disasm::{self, Syntax};
+use chip8_core::gdb::{GdbStatus, GdbStub};
+use chip8_core::movie::Movie;
+use chip8_core::rewind::Rewind;
+use chip8_core::romdb::{self, RomConfig, RomDatabase};
+use chip8_core::screenshot::{ImageFormat, Palette, Screenshot, DEFAULT_PALETTE};
+use chip8_core::trace::{TraceFormat, Tracer};
+use chip8_core::video::{Recorder, VideoFormat};
 use chip8_core::*;
 use sdl2::video::Window;
 use sdl2::render::Canvas;
@@ -5,23 +20,268 @@ use sdl2::event::Event;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::keyboard::Keycode;
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use std::env;
 
 const SCALE: u32 = 15;
 const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
 const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
-const TICKS_PER_FRAME: usize = 10;
+const NUM_SAVE_SLOTS: u8 = 10;
+const DEFAULT_REWIND_MB: usize = 16;
 
 fn main() {
     let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        print!("Usage: cargo run path/to/game");
+
+    // `asm` subcommand builds a ROM instead of running one
+    if args.get(1).map(String::as_str) == Some("asm") {
+        assemble_rom(&args[2..]);
         return;
     }
 
+    // Optional flags come before the ROM path
+    let mut quirks = None;
+    let mut mode = None;
+    let mut rewind_mb = DEFAULT_REWIND_MB;
+    let mut ips = None;
+    let mut seed = None;
+    let mut rng_algorithm = RngAlgorithm::default();
+    let mut tone = DEFAULT_FREQUENCY;
+    let mut volume = DEFAULT_VOLUME;
+    let mut muted = false;
+    let mut audio_wav = None;
+    let mut disasm = false;
+    let mut syntax = Syntax::default();
+    let mut gdb_port = None;
+    let mut trace_path = None;
+    let mut trace_format = TraceFormat::default();
+    let mut record_path = None;
+    let mut movie_out = None;
+    let mut movie_in = None;
+    let mut db_path = None;
+    let mut rom_path = None;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--quirks" if i + 1 < args.len() => {
+                match Quirks::from_name(&args[i + 1]) {
+                    Some(q) => quirks = Some(q),
+                    None => {
+                        eprintln!("Unknown quirks preset: {} (expected vip, chip48, schip or xochip)", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--mode" if i + 1 < args.len() => {
+                match Mode::from_name(&args[i + 1]) {
+                    Some(m) => mode = Some(m),
+                    None => {
+                        eprintln!("Unknown mode: {} (expected chip8, schip or xochip)", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--rewind-mb" if i + 1 < args.len() => {
+                match args[i + 1].parse() {
+                    Ok(mb) => rewind_mb = mb,
+                    Err(_) => {
+                        eprintln!("Invalid rewind memory: {}", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--ips" if i + 1 < args.len() => {
+                match args[i + 1].parse() {
+                    Ok(n) if n > 0 => ips = Some(n),
+                    _ => {
+                        eprintln!("Invalid instructions per second: {}", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--seed" if i + 1 < args.len() => {
+                match args[i + 1].parse() {
+                    Ok(s) => seed = Some(s),
+                    Err(_) => {
+                        eprintln!("Invalid seed: {}", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--rng" if i + 1 < args.len() => {
+                match RngAlgorithm::from_name(&args[i + 1]) {
+                    Some(a) => rng_algorithm = a,
+                    None => {
+                        eprintln!("Unknown RNG: {} (expected chacha8 or xorshift)", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--tone" if i + 1 < args.len() => {
+                match args[i + 1].parse::<f32>() {
+                    Ok(hz) if hz > 0.0 => tone = hz,
+                    _ => {
+                        eprintln!("Invalid tone frequency: {}", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--volume" if i + 1 < args.len() => {
+                match args[i + 1].parse::<u8>() {
+                    Ok(pct) if pct <= 100 => volume = pct as f32 / 100.0,
+                    _ => {
+                        eprintln!("Invalid volume: {} (expected 0 - 100)", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--mute" => muted = true,
+            "--audio-wav" if i + 1 < args.len() => {
+                audio_wav = Some(PathBuf::from(&args[i + 1]));
+                i += 1;
+            },
+            "--disasm" => disasm = true,
+            "--syntax" if i + 1 < args.len() => {
+                match Syntax::from_name(&args[i + 1]) {
+                    Some(s) => syntax = s,
+                    None => {
+                        eprintln!("Unknown syntax: {} (expected cowgod or octo)", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--gdb" if i + 1 < args.len() => {
+                match args[i + 1].parse::<u16>() {
+                    Ok(port) => gdb_port = Some(port),
+                    Err(_) => {
+                        eprintln!("Invalid GDB port: {}", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            "--trace" if i + 1 < args.len() => {
+                trace_path = Some(PathBuf::from(&args[i + 1]));
+                i += 1;
+            },
+            "--record" if i + 1 < args.len() => {
+                let path = PathBuf::from(&args[i + 1]);
+                if VideoFormat::from_path(&path).is_none() {
+                    eprintln!("Unknown video format: {} (expected .gif or .y4m)", path.display());
+                    return;
+                }
+                record_path = Some(path);
+                i += 1;
+            },
+            "--record-movie" if i + 1 < args.len() => {
+                movie_out = Some(PathBuf::from(&args[i + 1]));
+                i += 1;
+            },
+            "--play-movie" if i + 1 < args.len() => {
+                movie_in = Some(PathBuf::from(&args[i + 1]));
+                i += 1;
+            },
+            "--db" if i + 1 < args.len() => {
+                db_path = Some(PathBuf::from(&args[i + 1]));
+                i += 1;
+            },
+            "--trace-format" if i + 1 < args.len() => {
+                match TraceFormat::from_name(&args[i + 1]) {
+                    Some(f) => trace_format = f,
+                    None => {
+                        eprintln!("Unknown trace format: {} (expected text or binary)", args[i + 1]);
+                        return;
+                    }
+                }
+                i += 1;
+            },
+            path if rom_path.is_none() => rom_path = Some(path.to_string()),
+            _ => {
+                rom_path = None;
+                break;
+            }
+        }
+        i += 1;
+    }
+
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None => {
+            println!("Usage: cargo run asm path/to/source.asm [path/to/game]");
+            println!("       cargo run --disasm [--mode chip8|schip|xochip] [--syntax cowgod|octo] path/to/game");
+            print!("       cargo run [--mode chip8|schip|xochip] [--quirks vip|chip48|schip|xochip] [--rewind-mb N] [--ips N] [--seed N] [--rng chacha8|xorshift] [--tone HZ] [--volume 0-100] [--mute] [--audio-wav out.wav] [--gdb PORT] [--trace out.log] [--trace-format text|binary] [--record out.gif|out.y4m] [--record-movie out.c8m | --play-movie in.c8m] [--db programs.json] path/to/game");
+            return;
+        }
+    };
+
+    if disasm {
+        print_listing(&rom_path, mode.unwrap_or_default(), syntax);
+        return;
+    }
+
+    // Movies step whole frames, a GDB stop partway through one would put replay out of step
+    if gdb_port.is_some() && (movie_out.is_some() || movie_in.is_some()) {
+        eprintln!("--gdb can't be used with --record-movie or --play-movie");
+        return;
+    }
+
+    // Open the ROM file and read it into a buffer
+    let mut rom = File::open(&rom_path).expect("Unable to open file");
+    let mut buffer = Vec::new();
+    rom.read_to_end(&mut buffer).unwrap();
+
+    // Settings for known ROMs. Local database files found by themselves win over the bundled
+    // one, and --db wins over those
+    let mut db = RomDatabase::bundled();
+    let found = romdb::local_paths(Path::new(&rom_path)).into_iter().filter(|path| path.is_file());
+    for path in found.chain(db_path) {
+        let local = fs::read_to_string(&path).map_err(|e| e.to_string())
+            .and_then(|text| RomDatabase::parse(&text).map_err(|e| e.to_string()));
+        match local {
+            Ok(local) => db.extend(local),
+            Err(e) => {
+                eprintln!("Unable to read ROM database {}: {}", path.display(), e);
+                return;
+            }
+        }
+    }
+    let config = db.lookup(&buffer).cloned().unwrap_or_default();
+    if let Some(title) = &config.title {
+        println!("Found {} in the ROM database", title);
+    }
+
+    // Flags win over the database. Its quirks belong to its mode, another mode brings its own
+    let db_quirks = config.quirks.filter(|_| mode.is_none() || mode == config.mode);
+    let mode = mode.or(config.mode).unwrap_or_default();
+    let quirks = quirks.or(db_quirks);
+    let ips = ips.or(config.ips).unwrap_or(DEFAULT_IPS);
+    let palette = config.palette.unwrap_or(DEFAULT_PALETTE);
+
+    // Wait for the debugger before opening the window, it stays in charge from then on
+    let mut gdb = match gdb_port {
+        Some(port) => match wait_for_gdb(port) {
+            Ok(stub) => Some(stub),
+            Err(e) => {
+                eprintln!("Unable to start GDB stub on port {}: {}", port, e);
+                return;
+            }
+        },
+        None => None,
+    };
+
     // Setup SDL
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
@@ -39,16 +299,116 @@ fn main() {
     // SDL2 Event Pump polls for every loop
     let mut event_pump = sdl_context.event_pump().unwrap();
 
+    // Sound timer tone, either played through SDL or dumped to a WAV file
+    let mut wave = SquareWave::new(tone, volume);
+    wave.muted = muted;
+    let mut audio = match &audio_wav {
+        Some(path) => match Audio::wav(path, wave) {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                eprintln!("Unable to write {}: {}", path.display(), e);
+                return;
+            }
+        },
+        None => match Audio::sdl(&sdl_context, wave) {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                eprintln!("No audio: {}", e);     // Keep running silently
+                None
+            }
+        },
+    };
+
     //------------INITIALIZE EMU--------------//
-    let mut chip8 = Emu::new();
+    let mut chip8 = Emu::with_quirks(quirks.unwrap_or(mode.quirks()));     // Quirks follow the mode unless asked for
+    chip8.set_mode(mode);
+    chip8.set_rng_algorithm(rng_algorithm, seed.unwrap_or(chip8.seed()));   // Random seed unless one was given
 
-    // Open the ROM file, read into a buffer, and load the buffer.
-    let mut rom = File::open(&args[1]).expect("Unable to open file");
-    let mut buffer = Vec::new();
-    rom.read_to_end(&mut buffer).unwrap();
-    chip8.load(&buffer);
+    if let Err(e) = chip8.load(&buffer) {
+        eprintln!("Unable to load ROM: {}", e);
+        return;
+    }
+
+    // A movie being played sets the machine up its own way and supplies the keys
+    let movie = match &movie_in {
+        Some(path) => {
+            let movie = fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|text| Movie::parse(&text).map_err(|e| e.to_string()));
+            match movie.and_then(|m| m.setup(&buffer).map(|emu| (m, emu)).map_err(|e| e.to_string())) {
+                Ok((movie, emu)) => {
+                    chip8 = emu;
+                    Some(movie)
+                },
+                Err(e) => {
+                    eprintln!("Unable to replay {}: {}", path.display(), e);
+                    return;
+                }
+            }
+        },
+        None => None,
+    };
+    let mut playing = movie.as_ref();
+
+    // Real time paces the machine, the display's refresh rate doesn't matter
+    let mut clock = Clock::new(ips);
+    if let Some(movie) = playing {
+        clock.set_ips(movie.ticks_per_frame as u32 * TIMER_HZ);
+    }
+
+    // Movies store a fixed number of instructions per frame
+    let mut recording = match (&movie_out, clock.ticks_per_frame()) {
+        (Some(_), Some(ticks)) => Some(Movie::new(&buffer, &chip8, ticks)),
+        (Some(_), None) => {
+            eprintln!("Recording a movie needs --ips to be a multiple of {}", TIMER_HZ);
+            return;
+        },
+        (None, _) => None,
+    };
+
+    // Timer ticks so far, what movie events are keyed by
+    let mut frame: u64 = 0;
+
+    // Every executed instruction goes to the trace file
+    if let Some(path) = &trace_path {
+        match File::create(path).and_then(|file| Tracer::new(Box::new(BufWriter::new(file)), trace_format, syntax)) {
+            Ok(tracer) => chip8.set_tracer(tracer),
+            Err(e) => {
+                eprintln!("Unable to write {}: {}", path.display(), e);
+                return;
+            }
+        }
+    }
+
+    // One video frame per timer tick, about the size of the window
+    let mut recorder = match &record_path {
+        Some(path) => {
+            let format = VideoFormat::from_path(path).unwrap_or_default();
+            match File::create(path).and_then(|file| Recorder::new(Box::new(BufWriter::new(file)), format, WINDOW_WIDTH / HIRES_WIDTH as u32, &palette)) {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    eprintln!("Unable to write {}: {}", path.display(), e);
+                    return;
+                }
+            }
+        },
+        None => None,
+    };
+
+    // Set once the core reports an error, the window stays up but emulation stops
+    let mut halted = false;
+
+    // Quick save slot used by F5 / F9, F6 / F7 pick the slot
+    let mut slot: u8 = 0;
+
+    // Per-frame history, played back while Backspace is held
+    let mut history = Rewind::new(rewind_mb * 1024 * 1024);
+    let mut rewinding = false;
+
+    // P pauses into the terminal debugger
+    let mut debugger = Debugger::new(syntax);
 
     // Main gameloop
+    let mut last_loop = Instant::now();
     'gameloop: loop {
         for evt in event_pump.poll_iter() {     // Checks if any events have been triggered
             match evt {
@@ -56,51 +416,371 @@ fn main() {
                     break 'gameloop;
                 },
 
+                Event::KeyDown{keycode: Some(Keycode::F5), repeat: false, ..} => {          // Quick save
+                    let path = state_path(&rom_path, slot);
+                    match fs::write(&path, chip8.save_state()) {
+                        Ok(()) => println!("Saved state to {}", path.display()),
+                        Err(e) => eprintln!("Unable to save state to {}: {}", path.display(), e),
+                    }
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::F9), repeat: false, ..} if movie_out.is_some() || playing.is_some() => {
+                    println!("Loading states is off while a movie records or plays");
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::F9), repeat: false, ..} => {          // Quick load
+                    let path = state_path(&rom_path, slot);
+                    match fs::read(&path).map_err(|e| e.to_string())
+                        .and_then(|data| chip8.load_state(&data).map_err(|e| e.to_string())) {
+                        Ok(()) => {
+                            println!("Loaded state from {}", path.display());
+                            history.clear();
+                            halted = chip8.has_exited();
+                            canvas.window_mut().set_title("Chip-8 Emulator").unwrap();
+                        },
+                        Err(e) => eprintln!("Unable to load state from {}: {}", path.display(), e),
+                    }
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::F12), repeat: false, ..} => {         // Screenshot
+                    let path = screenshot_path(&rom_path);
+                    let screenshot = Screenshot {
+                        scale: WINDOW_WIDTH / chip8.display_width() as u32,     // Same size as the window
+                        palette,
+                        ..Screenshot::default()
+                    };
+                    match File::create(&path).and_then(|file| screenshot.write(&chip8, BufWriter::new(file))) {
+                        Ok(()) => println!("Saved screenshot to {}", path.display()),
+                        Err(e) => eprintln!("Unable to save screenshot to {}: {}", path.display(), e),
+                    }
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::M), repeat: false, ..} => {           // Toggle mute
+                    if let Some(audio) = audio.as_mut() {
+                        let muted = audio.toggle_mute();
+                        println!("Sound {}", if muted { "muted" } else { "on" });
+                    }
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::P), repeat: false, ..} if movie_out.is_some() || playing.is_some() => {
+                    println!("The debugger is off while a movie records or plays");
+                },
+                Event::KeyDown{keycode: Some(Keycode::P), repeat: false, ..} => {           // Pause / resume
+                    if debugger.paused {
+                        debugger.resume();
+                    } else {
+                        debugger.pause(&chip8);
+                    }
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::Space), repeat: false, ..} => {       // Pause / resume without the debugger
+                    clock.paused = !clock.paused;
+                    if !halted {
+                        let title = if clock.paused { "Chip-8 Emulator - paused" } else { "Chip-8 Emulator" };
+                        canvas.window_mut().set_title(title).unwrap();
+                    }
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::N), ..} => {                          // Frame advance while paused
+                    clock.frame_advance();
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::Minus | Keycode::Equals), repeat: false, ..} if movie_out.is_some() || playing.is_some() => {
+                    println!("Speed changes are off while a movie records or plays");
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::Minus), repeat: false, ..} => {       // Slow down
+                    println!("Speed {} instructions per second", clock.slower());
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::Equals), repeat: false, ..} => {      // Speed up
+                    println!("Speed {} instructions per second", clock.faster());
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::Backspace), ..} => {                  // Rewind while held
+                    if movie_out.is_some() || playing.is_some() {
+                        println!("Rewind is off while a movie records or plays");
+                    } else {
+                        rewinding = true;
+                    }
+                },
+
+                Event::KeyUp{keycode: Some(Keycode::Backspace), ..} => {
+                    rewinding = false;
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::F6), repeat: false, ..} => {          // Previous slot
+                    slot = (slot + NUM_SAVE_SLOTS - 1) % NUM_SAVE_SLOTS;
+                    println!("Save slot {}", slot);
+                },
+
+                Event::KeyDown{keycode: Some(Keycode::F7), repeat: false, ..} => {          // Next slot
+                    slot = (slot + 1) % NUM_SAVE_SLOTS;
+                    println!("Save slot {}", slot);
+                },
+
                 Event::KeyDown{keycode: Some(key), ..} => {                            // Handles Keydown                                          
-                    if let Some(k) = key2btn(key) {
+                    if let (Some(k), None) = (key2btn(key).or_else(|| control2btn(key, &config)), playing) {
                         chip8.keypress(k, true);
+                        if let Some(movie) = recording.as_mut() {
+                            movie.record_key(frame, k as u8, true);
+                        }
                     }
                 },
 
                 Event::KeyUp{keycode: Some(key), ..} => {                              // Handles Keyup
-                    if let Some(k) = key2btn(key) {
+                    if let (Some(k), None) = (key2btn(key).or_else(|| control2btn(key, &config)), playing) {
                         chip8.keypress(k, false);
+                        if let Some(movie) = recording.as_mut() {
+                            movie.record_key(frame, k as u8, false);
+                        }
                     }
                 },
                 _ => ()
             }
         }
 
-        // Clock cycle
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.tick();
+        // Frames due since the last time round, each one ticks the timers once
+        let now = Instant::now();
+        let frames = clock.advance(now - last_loop);
+        last_loop = now;
+
+        for _ in 0..frames {
+            if chip8.has_exited() && !halted {
+                canvas.window_mut().set_title("Chip-8 Emulator - exited").unwrap();
+                halted = true;
+            }
+
+            // Replay stops where the recording did, checking the screen got to the same place
+            if let Some(movie) = playing {
+                if frame >= movie.frames {
+                    match movie.screen_matches(&chip8) {
+                        Some(true) => println!("Replay finished after {} frames, screen matches the recording", frame),
+                        Some(false) => println!("Replay finished after {} frames, screen differs from the recording", frame),
+                        None => println!("Replay finished after {} frames", frame),
+                    }
+                    canvas.window_mut().set_title("Chip-8 Emulator - replay finished").unwrap();
+                    halted = true;
+                    playing = None;
+                } else {
+                    for event in movie.events_at(frame) {
+                        chip8.keypress(event.key as usize, event.pressed);
+                    }
+                }
+            }
+
+            // Whether instructions ran this frame
+            let mut running = false;
+            let ticks = clock.frame_ticks();
+
+            if rewinding {
+                // Step back a frame instead of running one
+                if let Some(state) = history.pop() {
+                    match chip8.load_state(state) {
+                        Ok(()) => {
+                            if halted {
+                                canvas.window_mut().set_title("Chip-8 Emulator").unwrap();
+                            }
+                            halted = chip8.has_exited();
+                        },
+                        Err(e) => {
+                            // The machine is untouched, carry on from here without the history
+                            eprintln!("Rewind history is unusable, dropping it: {}", e);
+                            history.clear();
+                        },
+                    }
+                }
+            } else if let Some(stub) = gdb.as_mut() {
+                // GDB decides when the machine runs
+                match stub.run_frame(&mut chip8, ticks) {
+                    Ok(GdbStatus::Running) => {
+                        chip8.tick_timers();
+                        history.push(chip8.save_state());
+                        running = true;
+                    },
+                    Ok(GdbStatus::Halted) => (),
+                    Ok(GdbStatus::Detached) => {
+                        println!("GDB detached");
+                        gdb = None;
+                    },
+                    Err(e) => {
+                        eprintln!("GDB connection lost: {}", e);
+                        gdb = None;
+                    },
+                }
+            } else if !halted && !debugger.paused {
+                // Clock cycle, cut short by breakpoints
+                if let Err(e) = debugger.run(&mut chip8, ticks) {
+                    eprintln!("Emulation halted: {}", e);
+                    canvas.window_mut().set_title(&format!("Chip-8 Emulator - {}", e)).unwrap();
+                    halted = true;
+                }
+
+                chip8.tick_timers();
+                history.push(chip8.save_state());
+                running = !halted;
+            }
+
+            if running {
+                frame += 1;
+            }
+
+            // Frames where the timers ticked, so the video plays back at 60Hz
+            if running {
+                if let Some(r) = recorder.as_mut() {
+                    if let Err(e) = r.frame(&chip8) {
+                        eprintln!("Recording stopped: {}", e);
+                        recorder = None;
+                    }
+                }
+            }
+
+            // Only beep while the game is actually running
+            if let Some(audio) = audio.as_mut() {
+                if let Err(e) = audio.frame(chip8.is_beeping() && running) {
+                    eprintln!("Audio output failed: {}", e);
+                }
+            }
+        }
+
+        if debugger.paused {
+            debugger.poll(&mut chip8);
+        }
+
+        // Nothing ran, so nothing should be sounding
+        if frames == 0 && clock.paused {
+            if let Some(audio) = audio.as_mut() {
+                audio.silence();
+            }
         }
 
-        chip8.tick_timers();
-        
         // Draw screen
-        draw_screen(&chip8, &mut canvas);
+        draw_screen(&chip8, &mut canvas, &palette);
+    }
+
+    if let Some(audio) = audio {
+        if let Err(e) = audio.finish() {
+            eprintln!("Unable to finish audio output: {}", e);
+        }
+    }
+
+    if let (Some(mut movie), Some(path)) = (recording, &movie_out) {
+        movie.end(frame, &chip8);
+        match fs::write(path, movie.to_string()) {
+            Ok(()) => println!("Saved movie of {} frames to {}", frame, path.display()),
+            Err(e) => eprintln!("Unable to write {}: {}", path.display(), e),
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        println!("Recorded {} frames", recorder.frames());
+        if let Err(e) = recorder.finish() {
+            eprintln!("Unable to finish recording: {}", e);
+        }
+    }
+
+    if let Some(tracer) = chip8.take_tracer() {
+        if let Err(e) = tracer.finish() {
+            eprintln!("Unable to finish trace: {}", e);
+        }
+    }
+}
+
+// Print the disassembly of a ROM file
+fn print_listing(rom_path: &str, mode: Mode, syntax: Syntax) {
+    let rom = match fs::read(rom_path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("Unable to open {}: {}", rom_path, e);
+            return;
+        }
+    };
+
+    for instr in disasm::disassemble(&rom, START_ADDR, mode, syntax) {
+        let hex: Vec<String> = instr.bytes.chunks(2)
+            .map(|word| word.iter().map(|b| format!("{:02X}", b)).collect())
+            .collect();
+        println!("0x{:03X}: {:<10}{}", instr.addr, hex.join(" "), instr);
+    }
+}
+
+// Assemble a source file, the ROM goes next to it unless an output path is given
+fn assemble_rom(args: &[String]) {
+    let (source, out) = match args {
+        [source] => (Path::new(source), Path::new(source).with_extension("ch8")),
+        [source, out] => (Path::new(source), PathBuf::from(out)),
+        _ => {
+            println!("Usage: cargo run asm path/to/source.asm [path/to/game]");
+            return;
+        }
+    };
+
+    let rom = match asm::assemble_file(source) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = fs::write(&out, &rom) {
+        eprintln!("Unable to write {}: {}", out.display(), e);
+        std::process::exit(1);
     }
+    println!("Wrote {} bytes to {}", rom.len(), out.display());
+}
+
+// Accept one GDB connection on localhost
+fn wait_for_gdb(port: u16) -> io::Result<GdbStub<TcpStream>> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Waiting for GDB on 127.0.0.1:{}", port);
+    let (stream, addr) = listener.accept()?;
+    println!("GDB connected from {}", addr);
+    stream.set_nonblocking(true)?;
+    stream.set_nodelay(true)?;
+    Ok(GdbStub::new(stream))
+}
+
+// Save states sit next to the ROM, e.g. pong.ch8 -> pong.state0
+fn state_path(rom_path: &str, slot: u8) -> PathBuf {
+    Path::new(rom_path).with_extension(format!("state{}", slot))
+}
+
+// Screenshots sit next to the ROM too, numbered from the first free one, e.g. pong.ch8 -> pong.shot0.png
+fn screenshot_path(rom_path: &str) -> PathBuf {
+    let extension = ImageFormat::Png.extension();
+    (0..)
+        .map(|n| Path::new(rom_path).with_extension(format!("shot{}.{}", n, extension)))
+        .find(|path| !path.exists())
+        .expect("Ran out of screenshot numbers")
 }
 
 // Draw screen
-fn draw_screen(emu: &Emu, canvas: &mut Canvas<Window>) {
-    // Clear canvas as black
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
+fn draw_screen(emu: &Emu, canvas: &mut Canvas<Window>, palette: &Palette) {
+    // Clear canvas to the background color
+    let (r, g, b) = palette[0];
+    canvas.set_draw_color(Color::RGB(r, g, b));
     canvas.clear();
 
     let screen_buf = emu.get_display();
+    let width = emu.display_width() as u32;
+    let height = emu.display_height() as u32;
 
-    // Now set draw color white, iterate through each point and see if it should be drawn
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    // Iterate through each point and draw the lit ones in their plane's color
     for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
+        if *pixel != 0 {
+            let (r, g, b) = palette[*pixel as usize % palette.len()];
+            canvas.set_draw_color(Color::RGB(r, g, b));
+
             // Convert our 1D array's index into a 2D (x,y) position
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
+            let x = i as u32 % width;
+            let y = i as u32 / width;
 
-            // Draw a rectangle at (x,y), scaled up by our SCALE value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
+            // Draw a rectangle at (x,y), stretched so the current resolution fills the window
+            let left = x * WINDOW_WIDTH / width;
+            let top = y * WINDOW_HEIGHT / height;
+            let right = (x + 1) * WINDOW_WIDTH / width;
+            let bottom = (y + 1) * WINDOW_HEIGHT / height;
+            let rect = Rect::new(left as i32, top as i32, right - left, bottom - top);
             canvas.fill_rect(rect).unwrap();
         }
     }
@@ -128,3 +808,21 @@ fn key2btn(key: Keycode) -> Option<usize> {
         _ => None,
     }
 }   
+
+// Arrow keys and friends press whatever the ROM database says the game uses for them
+fn control2btn(key: Keycode, config: &RomConfig) -> Option<usize> {
+    let control = match key {
+        Keycode:: Up =>     "up",
+        Keycode:: Down =>   "down",
+        Keycode:: Left =>   "left",
+        Keycode:: Right =>  "right",
+        Keycode:: Return => "a",
+        Keycode:: RShift => "b",
+        Keycode:: I =>      "player2Up",
+        Keycode:: K =>      "player2Down",
+        Keycode:: J =>      "player2Left",
+        Keycode:: L =>      "player2Right",
+        _ => return None,
+    };
+    config.key(control).map(usize::from)
+}
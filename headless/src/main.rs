@@ -0,0 +1,273 @@
+use chip8_core::audio::{SquareWave, WavWriter, SAMPLE_RATE};
+use chip8_core::clock::TIMER_HZ;
+use chip8_core::headless::{self, Condition, Outcome, Runner, TICKS_PER_FRAME};
+use chip8_core::movie::Movie;
+use chip8_core::romdb::{self, RomDatabase};
+use chip8_core::screenshot::{ImageFormat, Screenshot};
+use chip8_core::video::{Recorder, VideoFormat};
+use chip8_core::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// Exit codes, so CI can tell a failing ROM from a broken setup
+const EXIT_PASS: u8 = 0;
+const EXIT_FAIL: u8 = 1;
+const EXIT_ERROR: u8 = 2;   // Bad arguments, unreadable files or an emulation error
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args().collect();
+
+    // Optional flags come before the ROM path
+    let mut quirks = None;
+    let mut mode = None;
+    let mut seed = 0;       // Fixed unless asked for, so runs repeat
+    let mut rng_algorithm = RngAlgorithm::default();
+    let mut runner = Runner::default();
+    let mut ticks = None;
+    let mut show_ascii = false;
+    let mut screenshot = Screenshot::default();
+    let mut screenshot_path = None;
+    let mut record_path = None;
+    let mut wav_path = None;
+    let mut movie_path = None;
+    let mut expect_screen = None;
+    let mut db_path = None;
+    let mut rom_path = None;
+    let mut i = 1;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match (args[i].as_str(), value) {
+            ("--quirks", Some(name)) => match Quirks::from_name(name) {
+                Some(q) => quirks = Some(q),
+                None => return error_exit(&format!("Unknown quirks preset: {} (expected vip, chip48, schip or xochip)", name)),
+            },
+            ("--mode", Some(name)) => match Mode::from_name(name) {
+                Some(m) => mode = Some(m),
+                None => return error_exit(&format!("Unknown mode: {} (expected chip8, schip or xochip)", name)),
+            },
+            ("--seed", Some(text)) => match text.parse() {
+                Ok(s) => seed = s,
+                Err(_) => return error_exit(&format!("Invalid seed: {}", text)),
+            },
+            ("--rng", Some(name)) => match RngAlgorithm::from_name(name) {
+                Some(a) => rng_algorithm = a,
+                None => return error_exit(&format!("Unknown RNG: {} (expected chacha8 or xorshift)", name)),
+            },
+            ("--frames", Some(text)) => match text.parse() {
+                Ok(n) => runner.frames = n,
+                Err(_) => return error_exit(&format!("Invalid frame count: {}", text)),
+            },
+            ("--ticks", Some(text)) => match text.parse() {
+                Ok(n) if n > 0 => ticks = Some(n),
+                _ => return error_exit(&format!("Invalid ticks per frame: {}", text)),
+            },
+            ("--keys", Some(path)) => {
+                let script = fs::read_to_string(path).map_err(|e| e.to_string())
+                    .and_then(|text| headless::parse_script(&text).map_err(|e| e.to_string()));
+                match script {
+                    Ok(events) => runner.script = events,
+                    Err(e) => return error_exit(&format!("Unable to read key script {}: {}", path, e)),
+                }
+            },
+            ("--until" | "--fail-if", Some(text)) => match Condition::parse(text) {
+                Some(c) if args[i] == "--until" => runner.pass.push(c),
+                Some(c) => runner.fail.push(c),
+                None => return error_exit(&format!("Invalid condition: {} (expected exit, pc=ADDR, vX=NN or mem[ADDR]=NN)", text)),
+            },
+            ("--movie", Some(path)) => movie_path = Some(path.clone()),
+            ("--expect-screen", Some(path)) => expect_screen = Some(path.clone()),
+            ("--db", Some(path)) => db_path = Some(PathBuf::from(path)),
+            ("--screenshot", Some(path)) => {
+                let path = PathBuf::from(path);
+                match ImageFormat::from_path(&path) {
+                    Some(format) => screenshot.format = format,
+                    None => return error_exit(&format!("Unknown image format: {} (expected .png or .pbm)", path.display())),
+                }
+                screenshot_path = Some(path);
+            },
+            ("--record", Some(path)) => {
+                let path = PathBuf::from(path);
+                if VideoFormat::from_path(&path).is_none() {
+                    return error_exit(&format!("Unknown video format: {} (expected .gif or .y4m)", path.display()));
+                }
+                record_path = Some(path);
+            },
+            ("--wav", Some(path)) => wav_path = Some(PathBuf::from(path)),
+            ("--scale", Some(text)) => match text.parse() {
+                Ok(n) if n > 0 => screenshot.scale = n,
+                _ => return error_exit(&format!("Invalid scale: {}", text)),
+            },
+            ("--ascii", _) => {
+                show_ascii = true;
+                i += 1;
+                continue;
+            },
+            (path, _) if rom_path.is_none() && !path.starts_with("--") => {
+                rom_path = Some(path.to_string());
+                i += 1;
+                continue;
+            },
+            (flag, _) => return error_exit(&format!("Unexpected argument: {}", flag)),
+        }
+        i += 2;
+    }
+
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None => {
+            println!("Usage: cargo run [--mode chip8|schip|xochip] [--quirks vip|chip48|schip|xochip] [--seed N] [--rng chacha8|xorshift] [--frames N] [--ticks N] [--keys script.txt] [--movie in.c8m] [--until COND]... [--fail-if COND]... [--expect-screen golden.pbm] [--ascii] [--screenshot out.png|out.pbm] [--record out.gif|out.y4m] [--wav out.wav] [--scale N] [--db programs.json] path/to/game");
+            println!("       COND is exit, pc=ADDR, vX=NN or mem[ADDR]=NN with numbers in hex");
+            println!("       Known ROMs get their mode, quirks, speed and colors from the ROM database, flags win");
+            println!("       The database also reads ~/.config/chip8/programs.json and programs.json next to the ROM");
+            println!("       A movie sets the mode, quirks, seed, keys and frames, and checks the final screen");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let rom = match fs::read(&rom_path) {
+        Ok(rom) => rom,
+        Err(e) => return error_exit(&format!("Unable to open {}: {}", rom_path, e)),
+    };
+
+    // Settings for known ROMs. Local database files found by themselves win over the bundled
+    // one, and --db wins over those
+    let mut db = RomDatabase::bundled();
+    let found = romdb::local_paths(Path::new(&rom_path)).into_iter().filter(|path| path.is_file());
+    for path in found.chain(db_path) {
+        let local = fs::read_to_string(&path).map_err(|e| e.to_string())
+            .and_then(|text| RomDatabase::parse(&text).map_err(|e| e.to_string()));
+        match local {
+            Ok(local) => db.extend(local),
+            Err(e) => return error_exit(&format!("Unable to read ROM database {}: {}", path.display(), e)),
+        }
+    }
+    let config = db.lookup(&rom).cloned().unwrap_or_default();
+
+    // Flags win over the database. Its quirks belong to its mode, another mode brings its own
+    let db_quirks = config.quirks.filter(|_| mode.is_none() || mode == config.mode);
+    let mode = mode.or(config.mode).unwrap_or_default();
+    let quirks = quirks.or(db_quirks);
+    runner.ticks_per_frame = ticks
+        .or(config.ips.map(|ips| ((ips + TIMER_HZ / 2) / TIMER_HZ).max(1) as usize))     // Nearest whole count, at least one
+        .unwrap_or(TICKS_PER_FRAME);
+    screenshot.palette = config.palette.unwrap_or(screenshot.palette);
+
+    //------------INITIALIZE EMU--------------//
+    let mut chip8 = Emu::with_quirks(quirks.unwrap_or(mode.quirks()));     // Quirks follow the mode unless asked for
+    chip8.set_mode(mode);
+    chip8.set_rng_algorithm(rng_algorithm, seed);
+
+    if let Err(e) = chip8.load(&rom) {
+        return error_exit(&format!("Unable to load ROM: {}", e));
+    }
+
+    // A movie replaces the machine setup and the key script with its own
+    let movie = match &movie_path {
+        Some(path) => {
+            let movie = fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|text| Movie::parse(&text).map_err(|e| e.to_string()));
+            match movie.and_then(|m| m.setup(&rom).map(|emu| (m, emu)).map_err(|e| e.to_string())) {
+                Ok((movie, emu)) => {
+                    chip8 = emu;
+                    runner = Runner { pass: runner.pass, fail: runner.fail, ..movie.runner() };
+                    Some(movie)
+                },
+                Err(e) => return error_exit(&format!("Unable to replay {}: {}", path, e)),
+            }
+        },
+        None => None,
+    };
+
+    // Every frame of the run goes to the video
+    let mut recorder = None;
+    if let Some(path) = &record_path {
+        let format = VideoFormat::from_path(path).unwrap_or_default();
+        match File::create(path).and_then(|file| Recorder::new(Box::new(BufWriter::new(file)), format, screenshot.scale, &screenshot.palette)) {
+            Ok(r) => recorder = Some(r),
+            Err(e) => return error_exit(&format!("Unable to write {}: {}", path.display(), e)),
+        }
+    }
+
+    // And a frame of the sound timer's tone to the WAV file
+    let mut wav = None;
+    if let Some(path) = &wav_path {
+        match File::create(path).and_then(|file| WavWriter::new(BufWriter::new(file), SAMPLE_RATE)) {
+            Ok(w) => wav = Some((w, SquareWave::default(), vec![0.0; (SAMPLE_RATE / TIMER_HZ) as usize])),
+            Err(e) => return error_exit(&format!("Unable to write {}: {}", path.display(), e)),
+        }
+    }
+
+    let mut record_error = None;
+    let mut wav_error = None;
+    let outcome = runner.run_with(&mut chip8, |emu| {
+        if let (Some(recorder), None) = (recorder.as_mut(), &record_error) {
+            record_error = recorder.frame(emu).err();
+        }
+        if let (Some((writer, wave, buffer)), None) = (wav.as_mut(), &wav_error) {
+            wave.fill(buffer, SAMPLE_RATE, emu.is_beeping());
+            wav_error = writer.write(buffer).err();
+        }
+    });
+    println!("{}: {}", rom_path, outcome);
+
+    // The run only passes if the screen ended up where it should
+    let mut screen_ok = true;
+    if let Some(false) = movie.as_ref().and_then(|m| m.screen_matches(&chip8)) {
+        println!("{}: final screen differs from the recording", rom_path);
+        screen_ok = false;
+    }
+    if let Some(path) = &expect_screen {
+        match fs::read(path) {
+            Ok(expected) if expected == Screenshot::pbm().encode(&chip8) => (),
+            Ok(_) => {
+                println!("{}: final screen differs from {}", rom_path, path);
+                screen_ok = false;
+            },
+            Err(e) => return error_exit(&format!("Unable to open {}: {}", path, e)),
+        }
+    }
+
+    if show_ascii {
+        print!("{}", headless::ascii(&chip8));
+    }
+    if let Some(path) = &screenshot_path {
+        let written = File::create(path).and_then(|file| screenshot.write(&chip8, BufWriter::new(file)));
+        if let Err(e) = written {
+            return error_exit(&format!("Unable to write {}: {}", path.display(), e));
+        }
+    }
+
+    if let (Some(recorder), Some(path)) = (recorder, &record_path) {
+        let finished = match record_error {
+            Some(e) => Err(e),
+            None => recorder.finish(),
+        };
+        if let Err(e) = finished {
+            return error_exit(&format!("Unable to write {}: {}", path.display(), e));
+        }
+    }
+
+    if let (Some((writer, _, _)), Some(path)) = (wav, &wav_path) {
+        let finished = match wav_error {
+            Some(e) => Err(e),
+            None => writer.finish().map(drop),
+        };
+        if let Err(e) = finished {
+            return error_exit(&format!("Unable to write {}: {}", path.display(), e));
+        }
+    }
+
+    ExitCode::from(match outcome {
+        Outcome::Passed { .. } if screen_ok => EXIT_PASS,
+        Outcome::Passed { .. } | Outcome::Failed { .. } | Outcome::TimedOut { .. } => EXIT_FAIL,
+        Outcome::Error { .. } => EXIT_ERROR,
+    })
+}
+
+fn error_exit(message: &str) -> ExitCode {
+    eprintln!("{}", message);
+    ExitCode::from(EXIT_ERROR)
+}